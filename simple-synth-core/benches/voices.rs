@@ -0,0 +1,65 @@
+//! Times 16 held voices through each rendering path of the [`VoiceManager`].
+//!
+//! Run with `cargo bench -p simple-synth-core`.
+
+use std::{hint::black_box, time::Instant};
+use simple_synth_core::{EnvelopeStage, SynthParam, VoiceManager, Waveform};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BLOCK_LEN: usize = 512;
+/// Blocks rendered by each path, 10 s of audio.
+const BLOCKS: usize = 940;
+
+/// Sixteen detuned sawtooth and square voices held through a swept filter, a patch the voice
+/// bank renders.
+fn held_voices() -> VoiceManager {
+    let mut manager = VoiceManager::new(SAMPLE_RATE);
+    for param in [
+        SynthParam::Waveform(Waveform::Sawtooth),
+        SynthParam::Oscillator2Waveform(Waveform::Square),
+        SynthParam::Oscillator2Fine(7.0),
+        SynthParam::OscillatorMix(0.5),
+        SynthParam::EnvelopeStage(EnvelopeStage::Attack, 0.01),
+        SynthParam::EnvelopeStage(EnvelopeStage::Sustain, 0.8),
+        SynthParam::Cutoff(0.5),
+        SynthParam::FilterEnvAmount(0.3),
+    ] {
+        manager.apply_param(param);
+    }
+    for note in 48..64 {
+        manager.note_on(note, 0.8);
+    }
+
+    manager
+}
+
+/// Prints the time taken to render each sample and the speed against real time.
+fn bench(name: &str, mut render: impl FnMut(&mut VoiceManager, &mut [f32], &mut [f32])) {
+    let mut manager = held_voices();
+    let mut left = vec![0.0; BLOCK_LEN];
+    let mut right = vec![0.0; BLOCK_LEN];
+
+    let start = Instant::now();
+    for _ in 0..BLOCKS {
+        render(&mut manager, &mut left, &mut right);
+        black_box((&left, &right));
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let samples = (BLOCKS * BLOCK_LEN) as f64;
+    println!(
+        "{name:<8} {:>8.1} ns/sample {:>8.1}x real time",
+        elapsed * 1e9 / samples,
+        samples / SAMPLE_RATE as f64 / elapsed,
+    );
+}
+
+fn main() {
+    bench("process", |manager, left, right| {
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            (*left, *right) = manager.process_voices();
+        }
+    });
+    bench("block", VoiceManager::process_block);
+    bench("bank", VoiceManager::process_bank);
+}
@@ -0,0 +1,243 @@
+use wide::f32x4;
+use super::{exponential::RESTART_FADE, ramp::Ramp, Envelope};
+
+/// Maximum number of breakpoints of a [`BreakpointEnvelope`].
+pub const MAX_BREAKPOINTS: usize = 16;
+
+/// End of a segment of a [`BreakpointEnvelope`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Breakpoint {
+    /// Time in seconds to reach the level from the previous breakpoint.
+    pub time: f32,
+    pub level: f32,
+    /// Curve of the segment from -1.0 to 1.0, 0.0 being linear.
+    pub curve: f32,
+}
+
+impl Breakpoint {
+    pub fn new(time: f32, level: f32, curve: f32) -> Self {
+        Self {
+            time: time.clamp(0.0, 30.0),
+            level: level.clamp(0.0, 1.0),
+            curve: curve.clamp(-1.0, 1.0),
+        }
+    }
+}
+
+/// Multi-segment envelope running through a list of breakpoints from the level it was triggered at.
+///
+/// While the key is held, the envelope stops at the sustain point, or cycles from the loop
+/// end back to the segment after the loop start, starting it from the loop end level. A
+/// release jumps to the segment after the sustain point (or the loop end).
+pub struct BreakpointEnvelope {
+    sample_rate: f32,
+    breakpoints: [Breakpoint; MAX_BREAKPOINTS],
+    count: usize,
+    sustain: Option<usize>,
+    /// Start and end breakpoints of the loop.
+    loop_points: Option<(usize, usize)>,
+    level: f32,
+    /// Breakpoint the current segment is heading to.
+    segment: usize,
+    ramp: Ramp,
+    /// Samples left in the current segment.
+    remaining: u32,
+    holding: bool,
+    released: bool,
+    /// Whether the envelope is fading out before restarting.
+    restarting: bool,
+    idle: bool,
+}
+
+impl BreakpointEnvelope {
+    pub fn new(sample_rate: f32) -> Self {
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+
+        Self {
+            sample_rate,
+            breakpoints: [Breakpoint::default(); MAX_BREAKPOINTS],
+            count: 0,
+            sustain: None,
+            loop_points: None,
+            level: 0.0,
+            segment: 0,
+            ramp: Ramp::HOLD,
+            remaining: 0,
+            holding: false,
+            released: false,
+            restarting: false,
+            idle: true,
+        }
+    }
+
+    /// Sets up to [`MAX_BREAKPOINTS`] breakpoints, the sustain point and the loop as breakpoint
+    /// indices. Out-of-range points and loops not ending after their start are ignored.
+    ///
+    /// A running segment finishes as planned.
+    pub fn set_breakpoints(&mut self, breakpoints: &[Breakpoint], sustain: Option<usize>, loop_points: Option<(usize, usize)>) {
+        let count = breakpoints.len().min(MAX_BREAKPOINTS);
+        let sustain = sustain.filter(|&index| index < count);
+        let loop_points = loop_points.filter(|&(start, end)| start < end && end < count);
+        if self.breakpoints[..self.count] == breakpoints[..count]
+            && self.sustain == sustain
+            && self.loop_points == loop_points
+        {
+            return;
+        }
+
+        self.breakpoints[..count].copy_from_slice(&breakpoints[..count]);
+        self.count = count;
+        self.sustain = sustain;
+        self.loop_points = loop_points;
+    }
+
+    /// Restarts the envelope from 0.0 after a short fade out from the current level.
+    pub fn restart(&mut self) {
+        if self.idle || self.level <= 0.0 {
+            self.trigger();
+            return;
+        }
+
+        let samples = (RESTART_FADE * self.sample_rate).round().max(1.0);
+        self.holding = false;
+        self.released = false;
+        self.restarting = true;
+        self.ramp = Ramp::new(self.level, 0.0, samples, 0.0);
+        self.remaining = samples as u32;
+    }
+
+    /// Current level.
+    pub(crate) fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Advances the envelope by `samples` samples, at most four, and returns their levels, the
+    /// lanes past `samples` repeating the last level.
+    pub(crate) fn process_x4(&mut self, samples: usize) -> f32x4 {
+        let mut levels = [0.0; 4];
+        for level in &mut levels[..samples] {
+            *level = self.process();
+        }
+        levels[samples..].fill(self.level);
+
+        f32x4::from(levels)
+    }
+
+    /// Breakpoint a release jumps past.
+    fn release_point(&self) -> Option<usize> {
+        self.sustain.or(self.loop_points.map(|(_, end)| end))
+    }
+
+    /// Heads to the breakpoint at `index` from the current level, or stops after the last one.
+    fn start_segment(&mut self, index: usize) {
+        if index >= self.count {
+            self.idle = true;
+            return;
+        }
+
+        let breakpoint = self.breakpoints[index];
+        let samples = (breakpoint.time * self.sample_rate).round().max(1.0);
+        self.segment = index;
+        self.ramp = Ramp::new(self.level, breakpoint.level, samples, breakpoint.curve);
+        self.remaining = samples as u32;
+    }
+
+    /// Continues after reaching the breakpoint at `index`.
+    fn reached(&mut self, index: usize) {
+        if !self.released {
+            if let Some((start, end)) = self.loop_points
+                && index == end
+            {
+                self.start_segment(start + 1);
+                return;
+            }
+            if self.sustain == Some(index) {
+                self.holding = true;
+                return;
+            }
+        }
+
+        self.start_segment(index + 1);
+    }
+}
+
+impl Envelope for BreakpointEnvelope {
+    type Output = f32;
+
+    fn process(&mut self) -> f32 {
+        if self.idle || self.holding {
+            return self.level;
+        }
+
+        self.level = self.ramp.next(self.level);
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining == 0 && self.restarting {
+            self.level = 0.0;
+            self.restarting = false;
+            self.start_segment(0);
+        } else if self.remaining == 0 {
+            self.level = self.breakpoints[self.segment].level;
+            self.reached(self.segment);
+        }
+
+        self.level
+    }
+
+    fn trigger(&mut self) {
+        self.holding = false;
+        self.released = false;
+        self.restarting = false;
+        self.idle = false;
+        self.start_segment(0);
+    }
+
+    fn release(&mut self) {
+        self.released = true;
+        self.holding = false;
+        if self.idle {
+            return;
+        }
+
+        // A restart without a release point fades out and runs through every segment.
+        if let Some(point) = self.release_point()
+            && (self.restarting || self.segment <= point)
+        {
+            self.restarting = false;
+            self.start_segment(point + 1);
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// The segments come from the breakpoints, so the stage times are ignored.
+    fn set_delay_time(&mut self, _delay_time: f32) {}
+
+    fn set_attack_time(&mut self, _attack_time: f32) {}
+
+    fn set_hold_time(&mut self, _hold_time: f32) {}
+
+    fn set_decay_time(&mut self, _decay_time: f32) {}
+
+    fn set_sustain_level(&mut self, _sustain_level: f32) {}
+
+    fn set_release_time(&mut self, _release_time: f32) {}
+
+    /// Applies from the next segment.
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 {
+            self.sample_rate = sample_rate;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.level = 0.0;
+        self.segment = 0;
+        self.remaining = 0;
+        self.holding = false;
+        self.released = false;
+        self.restarting = false;
+        self.idle = true;
+    }
+}
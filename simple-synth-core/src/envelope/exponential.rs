@@ -0,0 +1,471 @@
+use wide::f32x4;
+use super::{
+    ramp::{Ramp, MIN_CURVE},
+    Envelope,
+    EnvelopeStage,
+};
+
+/// Length in seconds of the fade out before [`ExponentialEnvelope::restart`] restarts the envelope.
+pub(super) const RESTART_FADE: f32 = 0.002;
+
+/// DAHDSR envelope with a curvature per ramp stage.
+///
+/// Each stage takes the configured time whatever its curve and the distance it covers, and
+/// bends between straight (curve 0.0), fast-then-settling like an analog RC stage (1.0) and
+/// slow-then-accelerating (-1.0).
+///
+/// Changing a time or curve while its stage runs re-times the stage from the level it started
+/// at, so the level carries on from where it is without a jump.
+pub struct ExponentialEnvelope {
+    sample_rate: f32,
+    delay_time: f32,
+    attack_time: f32,
+    hold_time: f32,
+    decay_time: f32,
+    sustain_level: f32,
+    release_time: f32,
+    /// Curves of the attack, decay and release stages, from -1.0 to 1.0.
+    curves: [f32; 3],
+    level: f32,
+    stage: EnvelopeStage,
+    /// Whether the release stage is the fade out of a restart.
+    restarting: bool,
+    /// Samples left in the delay or hold stage.
+    remaining: f32,
+    /// Level the current ramp stage started from.
+    stage_start: f32,
+    /// Ramp of the current stage.
+    ramp: Ramp,
+}
+
+impl ExponentialEnvelope {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            delay_time: 0.0,
+            attack_time: 0.1,
+            hold_time: 0.0,
+            decay_time: 0.2,
+            sustain_level: 0.8,
+            release_time: 1.0,
+            curves: [0.0; 3],
+            level: 0.0,
+            stage: EnvelopeStage::Idle,
+            restarting: false,
+            remaining: 0.0,
+            stage_start: 0.0,
+            ramp: Ramp::HOLD,
+        }
+    }
+
+    /// Sets the curve of the attack, decay or release stage, from -1.0 to 1.0, 0.0 being linear.
+    pub fn set_curve(&mut self, stage: EnvelopeStage, curve: f32) {
+        let index = match stage {
+            EnvelopeStage::Attack => 0,
+            EnvelopeStage::Decay => 1,
+            EnvelopeStage::Release => 2,
+            EnvelopeStage::Idle | EnvelopeStage::Delay | EnvelopeStage::Hold | EnvelopeStage::Sustain => return,
+        };
+        let curve = curve.clamp(-1.0, 1.0);
+        if self.curves[index] != curve {
+            self.curves[index] = curve;
+            if self.stage == stage {
+                self.plan_stage();
+            }
+        }
+    }
+
+    /// Restarts the envelope from 0.0 after a short fade out from the current level.
+    pub fn restart(&mut self) {
+        if self.level > 0.0 {
+            self.restarting = true;
+            self.enter(EnvelopeStage::Release);
+        } else {
+            self.trigger();
+        }
+    }
+
+    /// Whether the envelope is fading out before restarting.
+    pub(crate) fn is_restarting(&self) -> bool {
+        self.restarting
+    }
+
+    /// Whether the envelope is in its release stage after a note-off.
+    pub(crate) fn is_released(&self) -> bool {
+        self.stage == EnvelopeStage::Release && !self.restarting
+    }
+
+    /// Whether the envelope is a plain ADSR with linear stages, without delay or hold.
+    pub(crate) fn is_linear_adsr(&self) -> bool {
+        self.curves.iter().all(|curve| curve.abs() < MIN_CURVE) && self.delay_time <= 0.0 && self.hold_time <= 0.0
+    }
+
+    /// Current stage and level.
+    pub(crate) fn state(&self) -> (EnvelopeStage, f32) {
+        (self.stage, self.level)
+    }
+
+    /// Restores a stage and level returned by [`ExponentialEnvelope::state`], a stage still
+    /// running carrying on with its ramp.
+    pub(crate) fn set_state(&mut self, stage: EnvelopeStage, level: f32) {
+        self.level = level;
+        if self.stage != stage {
+            self.stage = stage;
+            self.start_stage();
+        }
+    }
+
+    /// Signed level change per sample of the linear attack, decay and release stages, the
+    /// running stage starting from its start level and the others from the level they follow.
+    pub(crate) fn steps(&self) -> [f32; 3] {
+        [
+            (EnvelopeStage::Attack, 0.0, 1.0, self.attack_time),
+            (EnvelopeStage::Decay, 1.0, self.sustain_level, self.decay_time),
+            (EnvelopeStage::Release, self.sustain_level, 0.0, self.release_time),
+        ]
+        .map(|(stage, start, end, time)| {
+            let start = if self.stage == stage { self.stage_start } else { start };
+            (end - start) / (time * self.sample_rate).max(1.0)
+        })
+    }
+
+    pub(crate) fn sustain_level(&self) -> f32 {
+        self.sustain_level
+    }
+
+    /// Advances the envelope by `samples` samples, at most four, and returns their levels
+    /// with the number of samples processed before the envelope was idle.
+    ///
+    /// The stage is only checked once unless it ends within the samples, the lanes past
+    /// `samples` repeating the last level.
+    pub(crate) fn process_x4(&mut self, samples: usize) -> (f32x4, usize) {
+        let mut levels = [self.level; 4];
+        match self.stage {
+            EnvelopeStage::Idle => return (f32x4::splat(self.level), 0),
+            EnvelopeStage::Sustain => return (f32x4::splat(self.level), samples),
+            EnvelopeStage::Delay | EnvelopeStage::Hold if self.remaining > samples as f32 => {
+                self.remaining -= samples as f32;
+                return (f32x4::splat(self.level), samples);
+            },
+            EnvelopeStage::Attack | EnvelopeStage::Decay | EnvelopeStage::Release => {
+                let mut level = self.level;
+                for sample in &mut levels[..samples] {
+                    level = self.ramp.next(level);
+                    *sample = level;
+                }
+                let ended = match self.stage {
+                    EnvelopeStage::Attack => levels[..samples].iter().any(|&level| level >= 1.0),
+                    EnvelopeStage::Decay => levels[..samples].iter().any(|&level| level <= self.sustain_level),
+                    _ => levels[..samples].iter().any(|&level| level <= 0.0),
+                };
+                if !ended {
+                    self.level = level;
+                    levels[samples..].fill(level);
+                    return (f32x4::from(levels), samples);
+                }
+            },
+            EnvelopeStage::Delay | EnvelopeStage::Hold => (),
+        }
+
+        // The stage ends within the samples.
+        for processed in 0..samples {
+            if self.is_idle() {
+                levels[processed..].fill(self.level);
+                return (f32x4::from(levels), processed);
+            }
+            levels[processed] = self.process();
+        }
+        levels[samples..].fill(self.level);
+
+        (f32x4::from(levels), samples)
+    }
+
+    /// Plans the rest of the current stage from the current level.
+    fn start_stage(&mut self) {
+        self.stage_start = self.level;
+        self.plan_stage();
+    }
+
+    /// Plans the current stage from the level it started at, keeping the current level.
+    fn plan_stage(&mut self) {
+        let (end, time, curve) = match self.stage {
+            EnvelopeStage::Attack => (1.0, self.attack_time, self.curves[0]),
+            EnvelopeStage::Decay => (self.sustain_level, self.decay_time, self.curves[1]),
+            EnvelopeStage::Release => (0.0, self.release_time, self.curves[2]),
+            EnvelopeStage::Idle | EnvelopeStage::Delay | EnvelopeStage::Hold | EnvelopeStage::Sustain => return,
+        };
+        self.ramp = if self.restarting {
+            Ramp::new(self.stage_start, end, RESTART_FADE * self.sample_rate, 0.0)
+        } else {
+            Ramp::new(self.stage_start, end, time * self.sample_rate, curve)
+        };
+    }
+
+    /// Enters the given stage, skipping the delay and hold stages when their time is 0 s.
+    fn enter(&mut self, stage: EnvelopeStage) {
+        self.stage = match stage {
+            EnvelopeStage::Delay if self.delay_time <= 0.0 => EnvelopeStage::Attack,
+            EnvelopeStage::Hold if self.hold_time <= 0.0 => EnvelopeStage::Decay,
+            stage => stage,
+        };
+        match self.stage {
+            EnvelopeStage::Delay => self.remaining = self.delay_time * self.sample_rate,
+            EnvelopeStage::Hold => self.remaining = self.hold_time * self.sample_rate,
+            _ => self.start_stage(),
+        }
+    }
+}
+
+impl Envelope for ExponentialEnvelope {
+    type Output = f32;
+
+    fn process(&mut self) -> f32 {
+        match self.stage {
+            EnvelopeStage::Idle | EnvelopeStage::Sustain => return self.level,
+            EnvelopeStage::Delay | EnvelopeStage::Hold => {
+                self.remaining -= 1.0;
+                if self.remaining <= 0.0 {
+                    let next = if self.stage == EnvelopeStage::Delay { EnvelopeStage::Attack } else { EnvelopeStage::Decay };
+                    self.enter(next);
+                }
+                return self.level;
+            },
+            _ => (),
+        }
+
+        self.level = self.ramp.next(self.level);
+        match self.stage {
+            EnvelopeStage::Attack if self.level >= 1.0 => {
+                self.level = 1.0;
+                self.enter(EnvelopeStage::Hold);
+            },
+            EnvelopeStage::Decay if self.level <= self.sustain_level => {
+                self.level = self.sustain_level;
+                self.stage = EnvelopeStage::Sustain;
+            },
+            EnvelopeStage::Release if self.level <= 0.0 => {
+                self.level = 0.0;
+                if self.restarting {
+                    self.trigger();
+                } else {
+                    self.stage = EnvelopeStage::Idle;
+                }
+            },
+            _ => (),
+        }
+
+        self.level
+    }
+
+    fn trigger(&mut self) {
+        self.restarting = false;
+        self.enter(EnvelopeStage::Delay);
+    }
+
+    fn release(&mut self) {
+        self.restarting = false;
+        self.enter(EnvelopeStage::Release);
+    }
+
+    fn is_idle(&self) -> bool {
+        self.stage == EnvelopeStage::Idle
+    }
+
+    fn set_delay_time(&mut self, delay_time: f32) {
+        let delay_time = delay_time.clamp(0.0, 10.0);
+        if self.stage == EnvelopeStage::Delay {
+            self.remaining += (delay_time - self.delay_time) * self.sample_rate;
+        }
+        self.delay_time = delay_time;
+    }
+
+    fn set_attack_time(&mut self, attack_time: f32) {
+        let attack_time = attack_time.clamp(0.000001, 10.0);
+        if self.attack_time != attack_time {
+            self.attack_time = attack_time;
+            if self.stage == EnvelopeStage::Attack {
+                self.plan_stage();
+            }
+        }
+    }
+
+    fn set_hold_time(&mut self, hold_time: f32) {
+        let hold_time = hold_time.clamp(0.0, 10.0);
+        if self.stage == EnvelopeStage::Hold {
+            self.remaining += (hold_time - self.hold_time) * self.sample_rate;
+        }
+        self.hold_time = hold_time;
+    }
+
+    fn set_decay_time(&mut self, decay_time: f32) {
+        let decay_time = decay_time.clamp(0.000001, 10.0);
+        if self.decay_time != decay_time {
+            self.decay_time = decay_time;
+            if self.stage == EnvelopeStage::Decay {
+                self.plan_stage();
+            }
+        }
+    }
+
+    fn set_sustain_level(&mut self, sustain_level: f32) {
+        let sustain_level = sustain_level.clamp(0.0, 1.0);
+        if self.sustain_level != sustain_level {
+            self.sustain_level = sustain_level;
+            if self.stage == EnvelopeStage::Decay {
+                self.start_stage();
+            }
+        }
+    }
+
+    fn set_release_time(&mut self, release_time: f32) {
+        let release_time = release_time.clamp(0.000001, 10.0);
+        if self.release_time != release_time {
+            self.release_time = release_time;
+            if self.stage == EnvelopeStage::Release {
+                self.plan_stage();
+            }
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 && self.sample_rate != sample_rate {
+            if matches!(self.stage, EnvelopeStage::Delay | EnvelopeStage::Hold) {
+                self.remaining *= sample_rate / self.sample_rate;
+            }
+            self.sample_rate = sample_rate;
+            self.plan_stage();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stage = EnvelopeStage::Idle;
+        self.level = 0.0;
+        self.restarting = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One sample per millisecond, so stage times read as sample counts.
+    const SAMPLE_RATE: f32 = 1000.0;
+
+    /// Envelope with a 50 ms delay, 100 ms attack, 50 ms hold, 200 ms decay to 0.5 and 200 ms
+    /// release, each curved stage bent by `curve`.
+    fn envelope(curve: f32) -> ExponentialEnvelope {
+        let mut envelope = ExponentialEnvelope::new(SAMPLE_RATE);
+        envelope.set_delay_time(0.05);
+        envelope.set_attack_time(0.1);
+        envelope.set_hold_time(0.05);
+        envelope.set_decay_time(0.2);
+        envelope.set_sustain_level(0.5);
+        envelope.set_release_time(0.2);
+        for stage in [EnvelopeStage::Attack, EnvelopeStage::Decay, EnvelopeStage::Release] {
+            envelope.set_curve(stage, curve);
+        }
+
+        envelope
+    }
+
+    /// Samples processed in each stage, indexed by stage, releasing at the first sustained sample.
+    fn stage_lengths(envelope: &mut ExponentialEnvelope) -> [usize; 7] {
+        let mut lengths = [0; 7];
+        envelope.trigger();
+        for _ in 0..2000 {
+            let (stage, _) = envelope.state();
+            match stage {
+                EnvelopeStage::Idle => break,
+                EnvelopeStage::Sustain => envelope.release(),
+                _ => {
+                    envelope.process();
+                    lengths[stage as usize] += 1;
+                },
+            }
+        }
+
+        lengths
+    }
+
+    #[test]
+    fn stages_take_their_time_whatever_the_curve() {
+        for curve in [-1.0, 0.0, 1.0] {
+            let lengths = stage_lengths(&mut envelope(curve));
+            assert_eq!(lengths[EnvelopeStage::Delay as usize], 50, "curve {curve}");
+            assert_eq!(lengths[EnvelopeStage::Hold as usize], 50, "curve {curve}");
+            // The decay and release cover half the scale in their full time.
+            for (stage, expected) in [(EnvelopeStage::Attack, 100), (EnvelopeStage::Decay, 200), (EnvelopeStage::Release, 200)] {
+                let length = lengths[stage as usize];
+                assert!(length.abs_diff(expected) <= 1, "curve {curve}, {stage:?}: {length} samples");
+            }
+        }
+    }
+
+    #[test]
+    fn delay_and_hold_are_skipped_at_zero() {
+        let mut envelope = envelope(0.0);
+        envelope.set_delay_time(0.0);
+        envelope.set_hold_time(0.0);
+        let lengths = stage_lengths(&mut envelope);
+        assert_eq!(lengths[EnvelopeStage::Delay as usize], 0);
+        assert_eq!(lengths[EnvelopeStage::Hold as usize], 0);
+        assert!(lengths[EnvelopeStage::Attack as usize].abs_diff(100) <= 1);
+    }
+
+    /// Changes the envelope halfway through `stage` at 48 kHz and returns the level changes of
+    /// the sample before, the sample after and the one after that.
+    fn steps_around_change(mut envelope: ExponentialEnvelope, stage: EnvelopeStage, change: impl FnOnce(&mut ExponentialEnvelope)) -> [f32; 3] {
+        envelope.set_sample_rate(48000.0);
+        envelope.trigger();
+        while envelope.state().0 != stage {
+            if envelope.state().0 == EnvelopeStage::Sustain {
+                envelope.release();
+            } else {
+                envelope.process();
+            }
+        }
+        let mut levels = [envelope.state().1; 4];
+        for _ in 0..2400 {
+            levels[0] = levels[1];
+            levels[1] = envelope.process();
+        }
+        change(&mut envelope);
+        levels[2] = envelope.process();
+        levels[3] = envelope.process();
+
+        [0, 1, 2].map(|index| (levels[index + 1] - levels[index]).abs())
+    }
+
+    /// Asserts no step outpaces the steepest curve over a full-scale stage of 50 ms, the
+    /// shortest time the tests set.
+    fn assert_no_jump(steps: [f32; 3], label: &str) {
+        let steepest = 8.01 / (0.05 * 48000.0);
+        assert!(steps.iter().all(|&step| step <= steepest), "{label}: steps {steps:?}");
+    }
+
+    #[test]
+    fn time_changes_during_a_stage_do_not_jump() {
+        for curve in [-1.0, 0.0, 1.0] {
+            for (stage, set_time) in [
+                (EnvelopeStage::Attack, ExponentialEnvelope::set_attack_time as fn(&mut ExponentialEnvelope, f32)),
+                (EnvelopeStage::Decay, ExponentialEnvelope::set_decay_time),
+                (EnvelopeStage::Release, ExponentialEnvelope::set_release_time),
+            ] {
+                for time in [0.05, 0.8] {
+                    let steps = steps_around_change(envelope(curve), stage, |envelope| set_time(envelope, time));
+                    assert_no_jump(steps, &format!("curve {curve}, {stage:?} to {time} s"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn curve_changes_during_a_stage_do_not_jump() {
+        for stage in [EnvelopeStage::Attack, EnvelopeStage::Decay, EnvelopeStage::Release] {
+            for (from, to) in [(0.0, 1.0), (0.0, -1.0), (1.0, -1.0), (-1.0, 1.0)] {
+                let steps = steps_around_change(envelope(from), stage, |envelope| envelope.set_curve(stage, to));
+                assert_no_jump(steps, &format!("{stage:?} curve {from} to {to}"));
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
-mod linear;
+mod breakpoint;
+mod exponential;
+mod ramp;
 
-// TODO: exponential
-pub use linear::LinearEnvelope;
+pub use breakpoint::{Breakpoint, BreakpointEnvelope, MAX_BREAKPOINTS};
+pub use exponential::ExponentialEnvelope;
 
 /// Represents the different stages of an envelope generator.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -9,8 +11,12 @@ pub use linear::LinearEnvelope;
 pub enum EnvelopeStage {
     /// Idle phase, level is 0.0 (no sound, envelope generator is inactive).
     Idle,
+    /// Delay phase, level stays where it was before the attack starts.
+    Delay,
     /// Attack phase, level rises from 0.0 to 1.0.
     Attack,
+    /// Hold phase, level stays at 1.0 before the decay starts.
+    Hold,
     /// Decay phase, level falls from 1.0 to the sustain level.
     Decay,
     /// Sustain phase, level stays constant at the sustain level.
@@ -19,7 +25,29 @@ pub enum EnvelopeStage {
     Release,
 }
 
-/// Trait defining an envelope generator (ADSR).
+/// Represents the envelope generator driving a modulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EnvelopeType {
+    /// DAHDSR envelope, see [`ExponentialEnvelope`].
+    Adsr,
+    /// Looping multi-segment envelope, see [`BreakpointEnvelope`].
+    Breakpoint,
+}
+
+/// Represents how an envelope restarts when a sounding note is played again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RetriggerMode {
+    /// Fades out quickly and restarts the attack from 0.0.
+    Reset,
+    /// Restarts the attack from the current level.
+    Continue,
+    /// Keeps a held envelope running, a released one continues from the current level.
+    Legato,
+}
+
+/// Trait defining an envelope generator (DAHDSR, the delay and hold stages being skipped at 0 s).
 pub trait Envelope {
     /// The output sample type produced by the envelope generator.
     type Output;
@@ -28,19 +56,24 @@ pub trait Envelope {
     ///
     /// Returns a value of type `Output`.
     fn process(&mut self) -> Self::Output;
-    /// Starts the envelope by entering the [`EnvelopeStage::Attack`] stage.
+    /// Starts the envelope by entering the [`EnvelopeStage::Delay`] stage, or the
+    /// [`EnvelopeStage::Attack`] stage without delay.
     fn trigger(&mut self);
     /// Starts the [`EnvelopeStage::Release`] stage of the envelope generator.
     fn release(&mut self);
     /// Returns `true` if the envelope is in the [`EnvelopeStage::Idle`] stage.
     fn is_idle(&self) -> bool;
-    /// Sets the attack time of the envelope in milliseconds.
+    /// Sets the delay time of the envelope in seconds.
+    fn set_delay_time(&mut self, delay_time: f32);
+    /// Sets the attack time of the envelope in seconds.
     fn set_attack_time(&mut self, attack_time: f32);
-    /// Sets the decay time of the envelope in milliseconds.
+    /// Sets the hold time of the envelope in seconds.
+    fn set_hold_time(&mut self, hold_time: f32);
+    /// Sets the decay time of the envelope in seconds.
     fn set_decay_time(&mut self, decay_time: f32);
     /// Sets the sustain level.
     fn set_sustain_level(&mut self, sustain_level: f32);
-    /// Sets the release time of the envelope in milliseconds.
+    /// Sets the release time of the envelope in seconds.
     fn set_release_time(&mut self, release_time: f32);
     /// Sets the sample rate in hertz.
     fn set_sample_rate(&mut self, sample_rate: f32);
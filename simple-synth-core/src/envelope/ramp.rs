@@ -0,0 +1,46 @@
+/// Curvature `k` at a curve of 1.0, the level covering `(1 - e^(-k t)) / (1 - e^(-k))`
+/// of the ramp at progress `t`.
+const MAX_CURVATURE: f32 = 8.0;
+/// Curves closer to 0.0 than this ramp linearly.
+pub(super) const MIN_CURVE: f32 = 1e-3;
+
+/// Curved ramp of an envelope stage or segment.
+///
+/// The level follows `target + (level - target) * coefficient + increment` each sample.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Ramp {
+    target: f32,
+    coefficient: f32,
+    increment: f32,
+}
+
+impl Ramp {
+    /// Ramp holding the level.
+    pub(super) const HOLD: Self = Self { target: 0.0, coefficient: 1.0, increment: 0.0 };
+
+    /// Plans a ramp from `start` to `end` over `samples`, bending between slow-then-accelerating
+    /// (`curve` -1.0), straight (0.0) and fast-then-settling (1.0).
+    pub(super) fn new(start: f32, end: f32, samples: f32, curve: f32) -> Self {
+        let distance = end - start;
+
+        if samples <= 1.0 {
+            // Reaches the end on the next sample.
+            Self { target: end, coefficient: 0.0, increment: 0.0 }
+        } else if curve.abs() < MIN_CURVE {
+            Self { target: 0.0, coefficient: 1.0, increment: distance / samples }
+        } else {
+            // Glides towards a target beyond the end, crossing it after `samples`.
+            let curvature = MAX_CURVATURE * curve;
+            Self {
+                target: start + distance / (1.0 - (-curvature).exp()),
+                coefficient: (-curvature / samples).exp(),
+                increment: 0.0,
+            }
+        }
+    }
+
+    /// Level on the sample after `level`.
+    pub(super) fn next(&self, level: f32) -> f32 {
+        self.target + (level - self.target) * self.coefficient + self.increment
+    }
+}
@@ -0,0 +1,128 @@
+use std::f32::consts::TAU;
+
+/// Responses of the [`Biquad`] filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum BiquadType {
+    Lowpass,
+    Highpass,
+    /// Band-pass with a 0 dB peak.
+    Bandpass,
+    Notch,
+    Allpass,
+    /// Boosts or cuts a band around the frequency by the gain.
+    Peak,
+    /// Boosts or cuts below the frequency by the gain.
+    LowShelf,
+    /// Boosts or cuts above the frequency by the gain.
+    HighShelf,
+}
+
+/// Second-order IIR filter with coefficients from the RBJ audio EQ cookbook, in transposed
+/// direct form II.
+pub(crate) struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Creates a filter passing the input through unchanged.
+    pub(crate) fn new() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub(crate) fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+
+        output
+    }
+
+    /// Computes the coefficients of the response at `frequency` in hertz with quality `q` and
+    /// `gain` in decibels (used by the peak and shelves), keeping the filter state.
+    pub(crate) fn set(&mut self, response: BiquadType, frequency: f32, q: f32, gain: f32, sample_rate: f32) {
+        let frequency = frequency.clamp(10.0, 0.49 * sample_rate);
+        let omega = TAU * frequency / sample_rate;
+        let (sin, cos) = omega.sin_cos();
+        let alpha = sin / (2.0 * q.clamp(0.1, 40.0));
+        let amplitude = 10.0_f32.powf(gain / 40.0);
+
+        let [b0, b1, b2, a0, a1, a2] = match response {
+            BiquadType::Lowpass => {
+                let b = (1.0 - cos) / 2.0;
+                [b, 1.0 - cos, b, 1.0 + alpha, -2.0 * cos, 1.0 - alpha]
+            },
+            BiquadType::Highpass => {
+                let b = (1.0 + cos) / 2.0;
+                [b, -(1.0 + cos), b, 1.0 + alpha, -2.0 * cos, 1.0 - alpha]
+            },
+            BiquadType::Bandpass => [alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            BiquadType::Notch => [1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            BiquadType::Allpass => [1.0 - alpha, -2.0 * cos, 1.0 + alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            BiquadType::Peak => [
+                1.0 + alpha * amplitude,
+                -2.0 * cos,
+                1.0 - alpha * amplitude,
+                1.0 + alpha / amplitude,
+                -2.0 * cos,
+                1.0 - alpha / amplitude,
+            ],
+            BiquadType::LowShelf => {
+                let shelf = 2.0 * amplitude.sqrt() * alpha;
+                [
+                    amplitude * ((amplitude + 1.0) - (amplitude - 1.0) * cos + shelf),
+                    2.0 * amplitude * ((amplitude - 1.0) - (amplitude + 1.0) * cos),
+                    amplitude * ((amplitude + 1.0) - (amplitude - 1.0) * cos - shelf),
+                    (amplitude + 1.0) + (amplitude - 1.0) * cos + shelf,
+                    -2.0 * ((amplitude - 1.0) + (amplitude + 1.0) * cos),
+                    (amplitude + 1.0) + (amplitude - 1.0) * cos - shelf,
+                ]
+            },
+            BiquadType::HighShelf => {
+                let shelf = 2.0 * amplitude.sqrt() * alpha;
+                [
+                    amplitude * ((amplitude + 1.0) + (amplitude - 1.0) * cos + shelf),
+                    -2.0 * amplitude * ((amplitude - 1.0) + (amplitude + 1.0) * cos),
+                    amplitude * ((amplitude + 1.0) + (amplitude - 1.0) * cos - shelf),
+                    (amplitude + 1.0) - (amplitude - 1.0) * cos + shelf,
+                    2.0 * ((amplitude - 1.0) - (amplitude + 1.0) * cos),
+                    (amplitude + 1.0) - (amplitude - 1.0) * cos - shelf,
+                ]
+            },
+        };
+
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    /// Takes the coefficients of `other`, keeping the filter state.
+    pub(crate) fn copy_coefficients(&mut self, other: &Biquad) {
+        self.b0 = other.b0;
+        self.b1 = other.b1;
+        self.b2 = other.b2;
+        self.a1 = other.a1;
+        self.a2 = other.a2;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
@@ -0,0 +1,326 @@
+mod biquad;
+
+use wide::{f32x4, CmpEq};
+use biquad::{Biquad, BiquadType};
+
+/// Lowest and highest cutoff in hertz of the biquad modes, reached at cutoff 0.0 and 1.0.
+const BIQUAD_MIN_CUTOFF: f32 = 20.0;
+const BIQUAD_MAX_CUTOFF: f32 = 20000.0;
+/// Quality of the biquad modes at resonance 0.0, doubling five times up to resonance 1.0.
+const BIQUAD_MIN_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+/// Modulation of the cutoff the biquad lets through before recomputing its coefficients,
+/// about 12 cents.
+const BIQUAD_CUTOFF_STEP: f32 = 1e-3;
+
+/// Response of the filter, either the four-pole one-pole cascade or a biquad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FilterMode {
+    Lowpass,
+    Highpass,
+    Bandpass,
+    BiquadLowpass,
+    BiquadHighpass,
+    BiquadBandpass,
+    Notch,
+    Allpass,
+    /// Boosts or cuts a band around the cutoff by the filter gain.
+    Peak,
+    /// Boosts or cuts below the cutoff by the filter gain.
+    LowShelf,
+    /// Boosts or cuts above the cutoff by the filter gain.
+    HighShelf,
+}
+
+impl FilterMode {
+    /// Biquad response of the mode, or `None` for the one-pole cascade.
+    fn biquad_type(self) -> Option<BiquadType> {
+        match self {
+            FilterMode::Lowpass | FilterMode::Highpass | FilterMode::Bandpass => None,
+            FilterMode::BiquadLowpass => Some(BiquadType::Lowpass),
+            FilterMode::BiquadHighpass => Some(BiquadType::Highpass),
+            FilterMode::BiquadBandpass => Some(BiquadType::Bandpass),
+            FilterMode::Notch => Some(BiquadType::Notch),
+            FilterMode::Allpass => Some(BiquadType::Allpass),
+            FilterMode::Peak => Some(BiquadType::Peak),
+            FilterMode::LowShelf => Some(BiquadType::LowShelf),
+            FilterMode::HighShelf => Some(BiquadType::HighShelf),
+        }
+    }
+
+    /// Whether the mode is one of the biquad responses.
+    pub(crate) fn is_biquad(self) -> bool {
+        self.biquad_type().is_some()
+    }
+}
+
+/// Four-pole one-pole cascade, or a biquad for the biquad modes.
+///
+/// The biquad maps the cutoff from 0.0 to 1.0 exponentially from 20 Hz to 20 kHz and the
+/// resonance to its quality, recomputing its coefficients when one of them changes. A
+/// modulated cutoff only recomputes them once it has moved by [`BIQUAD_CUTOFF_STEP`], so a
+/// sweep updates them at a rate following its speed rather than every sample.
+pub struct Filter {
+    sample_rate: f32,
+    cutoff: f32,
+    cutoff_mod: f32,
+    resonance: f32,
+    feedback: f32,
+    buf0: f32,
+    buf1: f32,
+    buf2: f32,
+    buf3: f32,
+    mode: FilterMode,
+    biquad: Biquad,
+    /// Gain in decibels of the peak and shelf modes.
+    gain: f32,
+    /// Whether the biquad coefficients are out of date.
+    biquad_changed: bool,
+    /// Modulated cutoff the biquad coefficients were computed for.
+    biquad_cutoff: f32,
+}
+
+impl Filter {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut filter = Self {
+            sample_rate,
+            cutoff: 0.99,
+            cutoff_mod: 0.0,
+            resonance: 0.0,
+            feedback: 0.0,
+            buf0: 0.0,
+            buf1: 0.0,
+            buf2: 0.0,
+            buf3: 0.0,
+            mode: FilterMode::Lowpass,
+            biquad: Biquad::new(),
+            gain: 0.0,
+            biquad_changed: true,
+            biquad_cutoff: 0.99,
+        };
+        filter.calculate_feedback();
+
+        filter
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        if self.mode.is_biquad() {
+            self.update_biquad();
+            return self.biquad.process(input);
+        }
+
+        if input == 0.0 {
+            return input;
+        }
+
+        let calc_cutoff = self.calculate_cutoff();
+
+        // self.buf0 += calc_cutoff * (input - self.buf0 + (self.feedback * (self.buf0 - self.buf1)));
+        self.buf0 += calc_cutoff * (input - self.buf0);
+        self.buf1 += calc_cutoff * (self.buf0 - self.buf1);
+        self.buf2 += calc_cutoff * (self.buf1 - self.buf2);
+        self.buf3 += calc_cutoff * (self.buf2 - self.buf3);
+
+        match self.mode {
+            FilterMode::Lowpass => self.buf3,
+            FilterMode::Highpass => input - self.buf3,
+            FilterMode::Bandpass => self.buf0 - self.buf3,
+            _ => unreachable!("Biquad modes are rendered by the biquad."),
+        }
+    }
+
+    /// Filters a chunk of left samples in place and the same number of right samples through
+    /// `follower`, a filter with the same settings, modulating the cutoff of each sample.
+    ///
+    /// The one-pole cascade runs both channels side by side, a biquad one sample after the other.
+    pub(crate) fn process_stereo(&mut self, follower: &mut Filter, left: &mut [f32], right: &mut [f32], cutoff_mod: &[f32]) {
+        if self.mode.is_biquad() {
+            for ((left, right), &cutoff_mod) in left.iter_mut().zip(right.iter_mut()).zip(cutoff_mod) {
+                self.set_cutoff_mod(cutoff_mod);
+                follower.follow(self);
+                *left = self.process(*left);
+                *right = follower.process(*right);
+            }
+            return;
+        }
+
+        // Lanes 0 and 1 hold the left and right channels.
+        let (left_state, right_state) = (self.state(), follower.state());
+        let [mut buf0, mut buf1, mut buf2, mut buf3] = [0, 1, 2, 3].map(|stage| {
+            f32x4::from([left_state[stage], right_state[stage], 0.0, 0.0])
+        });
+        for ((left, right), &cutoff_mod) in left.iter_mut().zip(right.iter_mut()).zip(cutoff_mod) {
+            self.set_cutoff_mod(cutoff_mod);
+            let calc_cutoff = f32x4::splat(self.calculate_cutoff());
+            let input = f32x4::from([*left, *right, 0.0, 0.0]);
+            // Silent channels pass through without touching their state.
+            let silent = input.cmp_eq(f32x4::ZERO);
+
+            buf0 = silent.blend(buf0, buf0 + calc_cutoff * (input - buf0));
+            buf1 = silent.blend(buf1, buf1 + calc_cutoff * (buf0 - buf1));
+            buf2 = silent.blend(buf2, buf2 + calc_cutoff * (buf1 - buf2));
+            buf3 = silent.blend(buf3, buf3 + calc_cutoff * (buf2 - buf3));
+
+            let output = match self.mode {
+                FilterMode::Lowpass => buf3,
+                FilterMode::Highpass => input - buf3,
+                FilterMode::Bandpass => buf0 - buf3,
+                _ => unreachable!("Biquad modes are rendered by the biquad."),
+            };
+            [*left, *right, _, _] = silent.blend(input, output).to_array();
+        }
+
+        let [buf0, buf1, buf2, buf3] = [buf0, buf1, buf2, buf3].map(|stage| stage.to_array());
+        self.set_state([buf0[0], buf1[0], buf2[0], buf3[0]]);
+        follower.set_state([buf0[1], buf1[1], buf2[1], buf3[1]]);
+        follower.follow(self);
+    }
+
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        let cutoff = cutoff.clamp(0.01, 0.99);
+        self.biquad_changed |= self.cutoff != cutoff;
+        self.cutoff = cutoff;
+        self.calculate_feedback();
+    }
+
+    pub fn set_cutoff_mod(&mut self, cutoff_mod: f32) {
+        if self.cutoff_mod != cutoff_mod {
+            self.cutoff_mod = cutoff_mod;
+            self.biquad_changed |= (self.calculate_cutoff() - self.biquad_cutoff).abs() >= BIQUAD_CUTOFF_STEP;
+            self.calculate_feedback();
+        }
+    }
+
+    /// Takes the cutoff modulation of `leader`, a filter with the same settings, copying its
+    /// biquad coefficients instead of computing them again.
+    pub(crate) fn follow(&mut self, leader: &mut Filter) {
+        leader.update_biquad();
+        self.cutoff_mod = leader.cutoff_mod;
+        self.feedback = leader.feedback;
+        self.biquad.copy_coefficients(&leader.biquad);
+        self.biquad_cutoff = leader.biquad_cutoff;
+        self.biquad_changed = leader.biquad_changed;
+    }
+
+    pub fn set_resonance(&mut self, resonance: f32) {
+        let resonance = resonance.clamp(0.0, 1.0);
+        self.biquad_changed |= self.resonance != resonance;
+        self.resonance = resonance;
+        self.calculate_feedback();
+    }
+
+    /// Sets the gain in decibels of the peak and shelf modes.
+    pub fn set_gain(&mut self, gain: f32) {
+        let gain = gain.clamp(-24.0, 24.0);
+        self.biquad_changed |= self.gain != gain;
+        self.gain = gain;
+    }
+
+    pub fn set_mode(&mut self, mode: FilterMode) {
+        if self.mode != mode {
+            self.mode = mode;
+            self.biquad_changed = true;
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 && self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.biquad_changed = true;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.buf0 = 0.0;
+        self.buf1 = 0.0;
+        self.buf2 = 0.0;
+        self.buf3 = 0.0;
+        self.biquad.reset();
+    }
+
+    pub(crate) fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    pub(crate) fn mode(&self) -> FilterMode {
+        self.mode
+    }
+
+    /// States of the four one-pole stages.
+    pub(crate) fn state(&self) -> [f32; 4] {
+        [self.buf0, self.buf1, self.buf2, self.buf3]
+    }
+
+    /// Restores the stage states returned by [`Filter::state`].
+    pub(crate) fn set_state(&mut self, state: [f32; 4]) {
+        [self.buf0, self.buf1, self.buf2, self.buf3] = state;
+    }
+
+    fn calculate_cutoff(&self) -> f32 {
+        (self.cutoff + self.cutoff_mod).clamp(0.01, 0.99)
+    }
+
+    /// Recomputes the biquad coefficients if they are out of date and the mode is a biquad.
+    fn update_biquad(&mut self) {
+        let Some(response) = self.mode.biquad_type().filter(|_| self.biquad_changed) else {
+            return;
+        };
+        self.biquad_cutoff = self.calculate_cutoff();
+        let frequency = BIQUAD_MIN_CUTOFF * (BIQUAD_MAX_CUTOFF / BIQUAD_MIN_CUTOFF).powf(self.biquad_cutoff);
+        let q = BIQUAD_MIN_Q * 2.0_f32.powf(5.0 * self.resonance);
+        self.biquad.set(response, frequency, q, self.gain, self.sample_rate);
+        self.biquad_changed = false;
+    }
+
+    fn calculate_feedback(&mut self) {
+        self.feedback = self.resonance + (self.resonance / (1.0 - self.calculate_cutoff()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn biquad_filter() -> Filter {
+        let mut filter = Filter::new(48000.0);
+        filter.set_mode(FilterMode::BiquadLowpass);
+        filter.set_cutoff(0.5);
+        filter.set_resonance(0.3);
+        filter.process(0.0);
+        filter
+    }
+
+    #[test]
+    fn modulation_updates_the_biquad_in_steps() {
+        let mut filter = biquad_filter();
+        filter.set_cutoff_mod(0.5 * BIQUAD_CUTOFF_STEP);
+        assert!(!filter.biquad_changed);
+        filter.set_cutoff_mod(1.5 * BIQUAD_CUTOFF_STEP);
+        assert!(filter.biquad_changed);
+        filter.process(0.0);
+        assert_eq!(filter.biquad_cutoff, filter.calculate_cutoff());
+
+        // Steps count from the last update, not from the last modulation.
+        for step in 1..10 {
+            filter.set_cutoff_mod(BIQUAD_CUTOFF_STEP * (1.5 + 0.09 * step as f32));
+            assert!(!filter.biquad_changed, "step {step}");
+        }
+        filter.set_cutoff_mod(2.6 * BIQUAD_CUTOFF_STEP);
+        assert!(filter.biquad_changed);
+    }
+
+    #[test]
+    fn follower_matches_a_filter_computing_its_own_coefficients() {
+        let [mut leader, mut follower, mut reference] = [biquad_filter(), biquad_filter(), biquad_filter()];
+        for index in 0..4800 {
+            let cutoff_mod = 0.4 * index as f32 / 4800.0;
+            leader.set_cutoff_mod(cutoff_mod);
+            follower.follow(&mut leader);
+            reference.set_cutoff_mod(cutoff_mod);
+
+            let input = if index % 100 < 50 { 1.0 } else { -1.0 };
+            leader.process(input);
+            assert_eq!(follower.process(input), reference.process(input), "sample {index}");
+        }
+    }
+}
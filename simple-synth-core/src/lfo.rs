@@ -0,0 +1,127 @@
+use crate::rng::Rng;
+
+/// Low-frequency oscillator producing a triangle wave in `[-1.0, 1.0]`.
+pub struct Lfo {
+    phase: f32,
+    rate: f32,
+    sample_rate: f32,
+}
+
+impl Lfo {
+    pub fn new(sample_rate: f32) -> Self {
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+
+        Self {
+            phase: 0.0,
+            rate: 1.0,
+            sample_rate,
+        }
+    }
+
+    pub fn process(&mut self) -> f32 {
+        let sample = 1.0 - 4.0 * (self.phase - 0.5).abs();
+        self.phase = (self.phase + self.rate / self.sample_rate).rem_euclid(1.0);
+
+        sample
+    }
+
+    /// Sets the rate in hertz.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.clamp(0.0, 50.0);
+    }
+
+    /// Sets the phase, wrapped to `[0.0, 1.0)`.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = phase.rem_euclid(1.0);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 {
+            self.sample_rate = sample_rate;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+}
+
+/// Slowly wandering random signal in `[-1.0, 1.0]`, like the pitch drift of an analog oscillator.
+///
+/// Holds a random target for one period of the rate and glides towards it through a
+/// one-pole lowpass tuned to the same rate.
+pub struct Drift {
+    rng: Rng,
+    value: f32,
+    target: f32,
+    /// Samples left until the next target.
+    countdown: u32,
+    rate: f32,
+    /// Lowpass coefficient gliding towards the target.
+    coefficient: f32,
+    sample_rate: f32,
+}
+
+impl Drift {
+    pub fn new(sample_rate: f32) -> Self {
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+
+        let mut drift = Self {
+            rng: Rng::new(0),
+            value: 0.0,
+            target: 0.0,
+            countdown: 0,
+            rate: 0.5,
+            coefficient: 0.0,
+            sample_rate,
+        };
+        drift.update_coefficient();
+
+        drift
+    }
+
+    pub fn process(&mut self) -> f32 {
+        if self.countdown == 0 {
+            self.target = self.rng.next_bipolar();
+            self.countdown = (self.sample_rate / self.rate) as u32;
+        }
+        self.countdown -= 1;
+        self.value += self.coefficient * (self.target - self.value);
+
+        self.value
+    }
+
+    /// Current value without advancing.
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Sets how often the drift changes direction, in hertz.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.clamp(0.01, 20.0);
+        self.update_coefficient();
+    }
+
+    /// Restarts the random sequence from the given seed.
+    pub fn seed(&mut self, seed: u32) {
+        self.rng.seed(seed);
+        self.reset();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 {
+            self.sample_rate = sample_rate;
+            self.update_coefficient();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.value = 0.0;
+        self.target = 0.0;
+        self.countdown = 0;
+    }
+
+    fn update_coefficient(&mut self) {
+        self.coefficient = 1.0 - (-std::f32::consts::TAU * self.rate / self.sample_rate).exp();
+    }
+}
@@ -3,23 +3,129 @@
 
 mod envelope;
 mod filter;
+mod lfo;
 mod oscillator;
+mod rng;
 mod voice;
 mod voice_manager;
+mod wav;
 
-pub use envelope::EnvelopeStage;
+pub use envelope::{
+    Breakpoint,
+    BreakpointEnvelope,
+    Envelope,
+    EnvelopeStage,
+    EnvelopeType,
+    ExponentialEnvelope,
+    RetriggerMode,
+    MAX_BREAKPOINTS,
+};
 pub use filter::FilterMode;
-pub use oscillator::Waveform;
+pub use oscillator::{
+    FmMode,
+    Interpolation,
+    LoopMode,
+    NoiseColor,
+    OscillatorQuality,
+    OscillatorType,
+    PhaseMode,
+    PluckExcitation,
+    Sample,
+    ShapeMode,
+    SubWaveform,
+    Waveform,
+    Wavetable,
+    WavetableError,
+    MAX_PARTIALS,
+    MAX_UNISON_VOICES,
+    MAX_WAVETABLE_FRAMES,
+    MAX_WAVETABLE_FRAME_SIZE,
+};
+pub use voice::VelocityCurve;
 pub use voice_manager::VoiceManager;
+pub use wav::{WavError, WavFile};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
 pub enum SynthParam {
     EnvelopeStage(EnvelopeStage, f32),
+    EnvelopeCurve(EnvelopeStage, f32),
+    RetriggerMode(RetriggerMode),
     Waveform(Waveform),
+    NoiseColor(NoiseColor),
+    OscillatorQuality(OscillatorQuality),
+    OscillatorType(OscillatorType),
+    PhaseMode(PhaseMode),
+    WavetablePosition(f32),
+    PluckExcitation(PluckExcitation),
+    PluckDamping(f32),
+    PluckBrightness(f32),
+    PluckDecay(f32),
+    SampleInterpolation(Interpolation),
+    SampleLoopMode(LoopMode),
+    SampleLoopStart(f32),
+    SampleLoopEnd(f32),
+    SampleCrossfade(f32),
+    SampleRootKey(u8),
+    SampleFineTune(f32),
+    AdditivePartials(usize),
+    AdditiveBrightness(f32),
+    AdditiveOddEven(f32),
+    AdditiveStretch(f32),
+    Oscillator2Waveform(Waveform),
+    Oscillator2Type(OscillatorType),
+    Oscillator2PhaseMode(PhaseMode),
+    Oscillator2Octave(i32),
+    Oscillator2Semitone(i32),
+    Oscillator2Fine(f32),
+    OscillatorMix(f32),
+    SubWaveform(SubWaveform),
+    SubOctave(i32),
+    SubLevel(f32),
+    OscillatorSync(bool),
+    SyncPitch(f32),
+    SyncEnvAmount(f32),
+    FmMode(FmMode),
+    FmIndex(f32),
+    RingMod(f32),
+    PulseWidth(f32),
+    PwmDepth(f32),
+    PwmRate(f32),
+    ShapeMode(ShapeMode),
+    ShapeAmount(f32),
+    ShapeEnvAmount(f32),
+    DriftAmount(f32),
+    DriftRate(f32),
+    RandomDetune(f32),
+    UnisonVoices(usize),
+    UnisonDetune(f32),
+    UnisonDetuneCurve(f32),
+    UnisonRandomPhase(f32),
+    UnisonStereoSpread(f32),
     FilterMode(FilterMode),
     Cutoff(f32),
     Resonance(f32),
+    FilterGain(f32),
     SampleRate(f32),
     FilterEnvAmount(f32),
     FilterEnvStage(EnvelopeStage, f32),
+    FilterEnvCurve(EnvelopeStage, f32),
+    FilterEnvType(EnvelopeType),
+    BreakpointPitch(f32),
+    BreakpointCutoff(f32),
+    BreakpointMix(f32),
+    BreakpointPulseWidth(f32),
+    BreakpointShape(f32),
+    VelocityCurve(VelocityCurve),
+    VelocityAmplitude(f32),
+    VelocityFilterEnv(f32),
+    VelocityAttack(f32),
+}
+
+impl SynthParam {
+    /// Index of the variant, counting from 0 in declaration order.
+    pub(crate) fn discriminant(&self) -> u8 {
+        // SAFETY: `SynthParam` is `repr(u8)`, so every variant starts with its `u8` discriminant.
+        unsafe { *(self as *const Self).cast::<u8>() }
+    }
 }
@@ -1,12 +1,35 @@
 use std::f32::consts::PI;
-use rand::random;
-use super::{Oscillator, Waveform};
+use super::{
+    blep::{poly_blamp, poly_blep},
+    shape::{phase_distortion, wavefold, waveshape},
+    wrap_fraction,
+    HardSync,
+    Noise,
+    NoiseColor,
+    Oscillator,
+    OscillatorQuality,
+    ShapeMode,
+    Waveform,
+};
 
 pub struct BasicOscillator {
     phase: f32,
     sample_rate: f32,
     frequency: f32,
+    /// Frequency offset in hertz applied to the next tick.
+    frequency_offset: f32,
     waveform: Waveform,
+    quality: OscillatorQuality,
+    pulse_width: f32,
+    shape: ShapeMode,
+    shape_amount: f32,
+    noise: Noise,
+    /// Fraction of a sample since the phase wrapped in the last tick.
+    wrap_fraction: Option<f32>,
+    /// Pending hard sync requested by a master oscillator.
+    sync_fraction: Option<f32>,
+    /// Band-limiting residual of the last hard sync, added to the next sample.
+    sync_residual: f32,
 }
 
 impl BasicOscillator {
@@ -17,66 +40,230 @@ impl BasicOscillator {
             phase: 0.0,
             sample_rate,
             frequency: 440.0,
+            frequency_offset: 0.0,
             waveform: Waveform::Sine,
+            quality: OscillatorQuality::BandLimited,
+            pulse_width: 0.5,
+            shape: ShapeMode::Off,
+            shape_amount: 0.0,
+            noise: Noise::new(0),
+            wrap_fraction: None,
+            sync_fraction: None,
+            sync_residual: 0.0,
         }
     }
 
+    /// Phase of the next sample, from 0.0 to 1.0.
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Sets the phase of the next sample, wrapped to `[0.0, 1.0)`.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = phase.rem_euclid(1.0);
+    }
+
+    /// Phase increment per sample, negative when the phase runs backwards.
+    pub fn phase_increment(&self) -> f32 {
+        ((self.frequency + self.frequency_offset) / self.sample_rate).clamp(-0.5, 0.5)
+    }
+
     fn generate_sine(&self) -> f32 {
-        (2.0 * PI * self.phase).sin()
+        self.sine(self.phase)
+    }
+
+    /// Sine at the given phase, warped by the phase distortion.
+    fn sine(&self, phase: f32) -> f32 {
+        let phase = match self.shape {
+            ShapeMode::PhaseDistortion => phase_distortion(phase, self.shape_amount),
+            _ => phase,
+        };
+
+        (2.0 * PI * phase).sin()
+    }
+
+    /// Applies the wavefolder or waveshaper to the generated sample.
+    fn shape(&self, sample: f32) -> f32 {
+        match self.shape {
+            ShapeMode::Wavefolder => wavefold(sample, self.shape_amount),
+            ShapeMode::Waveshaper => waveshape(sample, self.shape_amount),
+            ShapeMode::Off | ShapeMode::PhaseDistortion => sample,
+        }
     }
 
     fn generate_square(&self) -> f32 {
-        if self.phase < 0.5 {
-            1.0
-        } else {
-            -1.0
+        let naive = naive_square(self.phase, self.pulse_width);
+
+        match self.quality {
+            OscillatorQuality::Naive => naive,
+            OscillatorQuality::BandLimited => {
+                // The falling edge sits at the pulse width.
+                let dt = self.phase_increment().abs();
+                naive + poly_blep(self.phase, dt) - poly_blep((self.phase + 1.0 - self.pulse_width).fract(), dt)
+            },
         }
     }
 
     fn generate_triangle(&self) -> f32 {
-        if self.phase < 0.5 {
-            4.0 * self.phase - 1.0
-        } else {
-            3.0 - 4.0 * self.phase
+        let naive = naive_triangle(self.phase);
+
+        match self.quality {
+            OscillatorQuality::Naive => naive,
+            OscillatorQuality::BandLimited => {
+                // The slope flips by 8.0 per cycle at both corners.
+                let dt = self.phase_increment().abs();
+                naive + 8.0 * dt * (poly_blamp(self.phase, dt) - poly_blamp((self.phase + 0.5).fract(), dt))
+            },
         }
     }
 
     fn generate_sawtooth(&self) -> f32 {
-        2.0 * self.phase - 1.0
+        let naive = naive_sawtooth(self.phase);
+
+        match self.quality {
+            OscillatorQuality::Naive => naive,
+            OscillatorQuality::BandLimited => naive - poly_blep(self.phase, self.phase_increment().abs()),
+        }
+    }
+
+    fn generate_noise(&mut self) -> f32 {
+        self.noise.process()
     }
 
-    fn generate_noise(&self) -> f32 {
-        2.0 * random::<f32>() - 1.0
+    /// Naive waveform value at the given phase, noise is treated as silence.
+    fn naive_sample(&self, phase: f32) -> f32 {
+        match self.waveform {
+            Waveform::Sine => self.sine(phase),
+            Waveform::Square => naive_square(phase, self.pulse_width),
+            Waveform::Triangle => naive_triangle(phase),
+            Waveform::Sawtooth => naive_sawtooth(phase),
+            Waveform::Noise => 0.0,
+        }
+    }
+
+    /// Restarts the cycle `fraction` of a sample before the next tick and returns
+    /// the band-limiting residual for the current sample.
+    fn sync(&mut self, fraction: f32, increment: f32) -> f32 {
+        let sync_phase = (self.phase + increment * (1.0 - fraction)).rem_euclid(1.0);
+        self.phase = (fraction * increment).rem_euclid(1.0);
+        self.wrap_fraction = Some(fraction);
+
+        if self.quality == OscillatorQuality::Naive {
+            return 0.0;
+        }
+
+        let (current, next) = sync_residuals(self.waveform, |phase| self.naive_sample(phase), sync_phase, fraction);
+        self.sync_residual = next;
+
+        current
     }
 }
 
+/// Band-limiting residuals of a hard sync `fraction` of a sample before the next tick,
+/// restarting the cycle from `sync_phase`, for the current and the next sample.
+pub(super) fn sync_residuals(waveform: Waveform, naive_sample: impl Fn(f32) -> f32, sync_phase: f32, fraction: f32) -> (f32, f32) {
+    // Naive waveform value as the phase approaches the end of the cycle.
+    let cycle_end = match waveform {
+        Waveform::Square | Waveform::Triangle => -1.0,
+        Waveform::Sawtooth => 1.0,
+        Waveform::Sine | Waveform::Noise => 0.0,
+    };
+    // The regular wrap correction of the new cycle already smooths a jump from
+    // the cycle end, so the residual after the sync only covers the remainder.
+    let jump = naive_sample(0.0) - naive_sample(sync_phase);
+    let remainder = cycle_end - naive_sample(sync_phase);
+
+    (0.5 * jump * fraction * fraction, -0.5 * remainder * (1.0 - fraction) * (1.0 - fraction))
+}
+
+pub(super) fn naive_square(phase: f32, pulse_width: f32) -> f32 {
+    if phase < pulse_width {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+pub(super) fn naive_triangle(phase: f32) -> f32 {
+    if phase < 0.5 {
+        4.0 * phase - 1.0
+    } else {
+        3.0 - 4.0 * phase
+    }
+}
+
+pub(super) fn naive_sawtooth(phase: f32) -> f32 {
+    2.0 * phase - 1.0
+}
+
 impl Oscillator for BasicOscillator {
     type Output = f32;
 
     fn tick(&mut self) -> f32 {
-        let sample = match self.waveform {
+        let mut sample = match self.waveform {
             Waveform::Sine => self.generate_sine(),
             Waveform::Square => self.generate_square(),
             Waveform::Triangle => self.generate_triangle(),
             Waveform::Sawtooth => self.generate_sawtooth(),
             Waveform::Noise => self.generate_noise(),
-        };
+        } + self.sync_residual;
+        self.sync_residual = 0.0;
 
-        self.phase = (self.phase + self.frequency / self.sample_rate).rem_euclid(1.0);
+        let increment = self.phase_increment();
+        if let Some(fraction) = self.sync_fraction.take() {
+            sample += self.sync(fraction, increment);
+        } else {
+            let phase = self.phase + increment;
+            self.wrap_fraction = wrap_fraction(phase, increment);
+            self.phase = phase.rem_euclid(1.0);
+        }
+        self.frequency_offset = 0.0;
 
-        sample
+        self.shape(sample)
     }
 
     fn set_frequency(&mut self, frequency: f32) {
         self.frequency = frequency.clamp(0.0, self.sample_rate / 2.0);
     }
 
+    fn modulate_frequency(&mut self, offset: f32) {
+        self.frequency_offset = offset;
+    }
+
     fn set_waveform(&mut self, waveform: Waveform) {
         if self.waveform != waveform {
             self.waveform = waveform;
         }
     }
 
+    fn set_quality(&mut self, quality: OscillatorQuality) {
+        if self.quality != quality {
+            self.quality = quality;
+        }
+    }
+
+    fn set_pulse_width(&mut self, pulse_width: f32) {
+        self.pulse_width = pulse_width.clamp(0.01, 0.99);
+    }
+
+    fn set_shape(&mut self, mode: ShapeMode) {
+        if self.shape != mode {
+            self.shape = mode;
+        }
+    }
+
+    fn set_shape_amount(&mut self, amount: f32) {
+        self.shape_amount = amount.clamp(0.0, 1.0);
+    }
+
+    fn set_noise_color(&mut self, color: NoiseColor) {
+        self.noise.set_color(color);
+    }
+
+    fn set_seed(&mut self, seed: u32) {
+        self.noise.seed(seed);
+    }
+
     fn set_sample_rate(&mut self, sample_rate: f32) {
         if sample_rate > 0.0 {
             self.sample_rate = sample_rate;
@@ -85,5 +272,103 @@ impl Oscillator for BasicOscillator {
 
     fn reset(&mut self) {
         self.phase = 0.0;
+        self.wrap_fraction = None;
+        self.sync_fraction = None;
+        self.sync_residual = 0.0;
+    }
+}
+
+impl HardSync for BasicOscillator {
+    type Wrap = Option<f32>;
+
+    fn wrapped(&self) -> Option<f32> {
+        self.wrap_fraction
+    }
+
+    fn hard_sync(&mut self, wrap: Option<f32>) {
+        self.sync_fraction = wrap.map(|fraction| fraction.clamp(0.0, 1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oscillator;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const FREQUENCY: f32 = 5000.0;
+
+    /// Alias ratio of the waveform at [`FREQUENCY`], whose aliases land on multiples of 1 kHz at 48 kHz.
+    fn alias_ratio(waveform: Waveform, quality: OscillatorQuality) -> f32 {
+        let mut oscillator = BasicOscillator::new(SAMPLE_RATE);
+        oscillator.set_waveform(waveform);
+        oscillator.set_quality(quality);
+        oscillator.set_frequency(FREQUENCY);
+        // 0.1 s holds whole periods of every multiple of 10 Hz.
+        let samples: Vec<f32> = (0..4800).map(|_| oscillator.tick()).collect();
+
+        oscillator::alias_ratio(&samples, FREQUENCY, 1000.0, SAMPLE_RATE)
+    }
+
+    #[test]
+    fn band_limited_sawtooth_aliases_less_than_naive() {
+        let naive = alias_ratio(Waveform::Sawtooth, OscillatorQuality::Naive);
+        let band_limited = alias_ratio(Waveform::Sawtooth, OscillatorQuality::BandLimited);
+        assert!(band_limited < naive / 10.0, "naive {naive}, band-limited {band_limited}");
+    }
+
+    /// Alias ratio of a sawtooth at 1.37 times [`FREQUENCY`] hard-synced to a master at
+    /// [`FREQUENCY`], the synced waveform repeating at the master frequency.
+    fn sync_alias_ratio(quality: OscillatorQuality) -> f32 {
+        let mut master = BasicOscillator::new(SAMPLE_RATE);
+        master.set_frequency(FREQUENCY);
+        let mut slave = BasicOscillator::new(SAMPLE_RATE);
+        slave.set_waveform(Waveform::Sawtooth);
+        slave.set_quality(quality);
+        slave.set_frequency(1.37 * FREQUENCY);
+        let samples: Vec<f32> = (0..4800)
+            .map(|_| {
+                master.tick();
+                slave.hard_sync(master.wrapped());
+                slave.tick()
+            })
+            .collect();
+
+        oscillator::alias_ratio(&samples, FREQUENCY, 1000.0, SAMPLE_RATE)
+    }
+
+    #[test]
+    fn band_limited_sync_aliases_less_than_naive() {
+        let naive = sync_alias_ratio(OscillatorQuality::Naive);
+        let band_limited = sync_alias_ratio(OscillatorQuality::BandLimited);
+        assert!(band_limited < naive / 10.0, "naive {naive}, band-limited {band_limited}");
+    }
+
+    #[test]
+    fn reports_wraps_in_both_directions() {
+        let mut oscillator = BasicOscillator::new(SAMPLE_RATE);
+        oscillator.set_frequency(0.0);
+        oscillator.set_phase(0.9);
+        oscillator.modulate_frequency(0.25 * SAMPLE_RATE);
+        oscillator.tick();
+        assert!((oscillator.wrapped().unwrap() - 0.6).abs() < 1e-5);
+
+        // Through-zero FM runs the phase back past 0.0, 0.4 of a sample into the tick.
+        oscillator.set_phase(0.1);
+        oscillator.modulate_frequency(-0.25 * SAMPLE_RATE);
+        oscillator.tick();
+        assert!((oscillator.wrapped().unwrap() - 0.6).abs() < 1e-5);
+        assert!((oscillator.phase() - 0.85).abs() < 1e-5);
+
+        oscillator.modulate_frequency(-0.25 * SAMPLE_RATE);
+        oscillator.tick();
+        assert_eq!(oscillator.wrapped(), None);
+    }
+
+    #[test]
+    fn band_limited_square_aliases_less_than_naive() {
+        let naive = alias_ratio(Waveform::Square, OscillatorQuality::Naive);
+        let band_limited = alias_ratio(Waveform::Square, OscillatorQuality::BandLimited);
+        assert!(band_limited < naive / 10.0, "naive {naive}, band-limited {band_limited}");
     }
 }
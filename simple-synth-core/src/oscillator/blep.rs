@@ -0,0 +1,74 @@
+use wide::{f32x4, CmpGt, CmpLt};
+
+/// Polynomial band-limited step residual for a rising discontinuity from -1.0 to 1.0.
+///
+/// `t` is the normalized phase in `[0.0, 1.0)` and `dt` the phase increment per sample.
+pub fn poly_blep(t: f32, dt: f32) -> f32 {
+    if dt <= 0.0 {
+        return 0.0;
+    }
+
+    if t < dt {
+        let x = t / dt;
+        -(1.0 - x) * (1.0 - x)
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt;
+        (x + 1.0) * (x + 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Polynomial band-limited ramp residual for a slope change of 1.0 per sample.
+///
+/// `t` is the normalized phase in `[0.0, 1.0)` and `dt` the phase increment per sample.
+pub fn poly_blamp(t: f32, dt: f32) -> f32 {
+    if dt <= 0.0 {
+        return 0.0;
+    }
+
+    if t < dt {
+        let x = 1.0 - t / dt;
+        x * x * x / 6.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt + 1.0;
+        x * x * x / 6.0
+    } else {
+        0.0
+    }
+}
+
+/// Four-lane version of [`poly_blep`], with a phase increment per lane.
+pub fn poly_blep_x4(t: f32x4, dt: f32x4) -> f32x4 {
+    let dt_v = dt.max(f32x4::splat(f32::MIN_POSITIVE));
+    let one = f32x4::ONE;
+
+    let x_after = t / dt_v;
+    let after = -(one - x_after) * (one - x_after);
+    let x_before = (t - one) / dt_v + one;
+    let before = x_before * x_before;
+
+    let residual = t.cmp_lt(dt_v).blend(
+        after,
+        t.cmp_gt(one - dt_v).blend(before, f32x4::ZERO),
+    );
+    dt.cmp_gt(f32x4::ZERO).blend(residual, f32x4::ZERO)
+}
+
+/// Four-lane version of [`poly_blamp`], with a phase increment per lane.
+pub fn poly_blamp_x4(t: f32x4, dt: f32x4) -> f32x4 {
+    let dt_v = dt.max(f32x4::splat(f32::MIN_POSITIVE));
+    let one = f32x4::ONE;
+    let six = f32x4::splat(6.0);
+
+    let x_after = one - t / dt_v;
+    let after = x_after * x_after * x_after / six;
+    let x_before = (t - one) / dt_v + one;
+    let before = x_before * x_before * x_before / six;
+
+    let residual = t.cmp_lt(dt_v).blend(
+        after,
+        t.cmp_gt(one - dt_v).blend(before, f32x4::ZERO),
+    );
+    dt.cmp_gt(f32x4::ZERO).blend(residual, f32x4::ZERO)
+}
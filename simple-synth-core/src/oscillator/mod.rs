@@ -1,10 +1,28 @@
+mod additive;
 mod basic;
+mod blep;
+mod multi;
+mod noise;
+mod pluck;
+mod sample;
+mod shape;
 mod simd;
+mod sub;
+mod unison;
+mod wavetable;
 
-// TODO: wavetables
-// TODO: anti-aliasing (e.g. PolyBLEP)
+pub use additive::{AdditiveOscillator, MAX_PARTIALS};
 pub use basic::BasicOscillator;
+pub use multi::MultiOscillator;
+pub use noise::{Noise, NoiseX4};
+pub use pluck::PluckOscillator;
+pub use sample::{Sample, SampleOscillator};
 pub use simd::SimdOscillator;
+pub use sub::SubOscillator;
+pub use unison::{UnisonOscillator, MAX_UNISON_VOICES};
+pub use wavetable::{
+    Wavetable, WavetableError, WavetableOscillator, MAX_WAVETABLE_FRAMES, MAX_WAVETABLE_FRAME_SIZE,
+};
 
 /// Represents the different waveform shapes an oscillator can generate.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -17,6 +35,124 @@ pub enum Waveform {
     Noise,
 }
 
+/// Represents the spectral colour of the [`Waveform::Noise`] shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum NoiseColor {
+    /// Flat spectrum.
+    White,
+    /// Falls 3 dB per octave.
+    Pink,
+    /// Falls 6 dB per octave, also known as red noise.
+    Brown,
+    /// Rises 3 dB per octave.
+    Blue,
+}
+
+/// Represents the waveform shapes of the [`SubOscillator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SubWaveform {
+    Square,
+    Sine,
+}
+
+/// Represents the sound source used by a voice oscillator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OscillatorType {
+    /// [`BasicOscillator`] with one of the [`Waveform`] shapes.
+    Basic,
+    /// [`WavetableOscillator`] morphing through the frames of a [`Wavetable`].
+    Wavetable,
+    /// [`PluckOscillator`] Karplus-Strong plucked string.
+    Pluck,
+    /// [`SampleOscillator`] playing back a recorded [`Sample`].
+    Sample,
+    /// [`AdditiveOscillator`] summing sine partials.
+    Additive,
+}
+
+/// Represents what plucks the string of a [`PluckOscillator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PluckExcitation {
+    /// White noise burst, a bright and rich attack.
+    Noise,
+    /// Noise lowpassed around the fourth harmonic, a softer attack.
+    FilteredNoise,
+    /// One sawtooth cycle, a clean and pitched attack.
+    Impulse,
+}
+
+/// Represents how a [`SampleOscillator`] repeats its loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LoopMode {
+    /// Plays the sample once and falls silent at its end.
+    Off,
+    /// Jumps from the loop end back to the loop start.
+    Forward,
+    /// Plays the loop forwards then backwards.
+    PingPong,
+}
+
+/// Represents how a [`SampleOscillator`] reads between the recorded samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Interpolation {
+    /// Straight line between the two nearest samples, cheap but dulls the highs.
+    Linear,
+    /// Cubic Hermite spline through the four nearest samples.
+    Hermite,
+}
+
+/// Represents the timbre shaping stage of the basic waveforms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ShapeMode {
+    /// No shaping.
+    Off,
+    /// Casio-style phase distortion of the sine, towards a resonant sawtooth.
+    PhaseDistortion,
+    /// Folds the waveform back on itself as it is driven past full scale.
+    Wavefolder,
+    /// Soft-clips the driven waveform.
+    Waveshaper,
+}
+
+/// Represents where an oscillator starts its cycle when a note starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PhaseMode {
+    /// Keeps running between notes like an analog oscillator, so a new note starts at a random phase.
+    FreeRunning,
+    /// Restarts the cycle at every note-on, giving the same attack every time.
+    Reset,
+}
+
+/// Represents how oscillator 2 modulates the frequency of oscillator 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FmMode {
+    /// No frequency modulation.
+    Off,
+    /// Linear FM, the modulated frequency stops at 0 Hz.
+    Linear,
+    /// Linear FM where the modulated frequency may cross 0 Hz and run the phase backwards.
+    ThroughZero,
+}
+
+/// Represents the rendering quality of the discontinuous waveforms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OscillatorQuality {
+    /// Naive waveforms, cheap but aliasing at high frequencies.
+    Naive,
+    /// Band-limited waveforms (PolyBLEP for square/sawtooth, PolyBLAMP for triangle).
+    BandLimited,
+}
+
 /// Trait defining an oscillator.
 pub trait Oscillator {
     /// The output sample type produced by the oscillator.
@@ -28,10 +164,88 @@ pub trait Oscillator {
     fn tick(&mut self) -> Self::Output;
     /// Sets the oscillator frequency in hertz.
     fn set_frequency(&mut self, frequency: f32);
+    /// Offsets the frequency in hertz for the next tick only.
+    ///
+    /// The resulting frequency may be negative, running the phase backwards (through-zero FM).
+    fn modulate_frequency(&mut self, offset: f32);
     /// Sets the waveform shape to generate, based on the [`Waveform`] enum.
     fn set_waveform(&mut self, waveform: Waveform);
+    /// Sets the rendering quality, based on the [`OscillatorQuality`] enum.
+    fn set_quality(&mut self, quality: OscillatorQuality);
+    /// Sets the duty cycle of the square wave, from 0.01 to 0.99.
+    fn set_pulse_width(&mut self, pulse_width: f32);
+    /// Sets the timbre shaping stage, based on the [`ShapeMode`] enum.
+    fn set_shape(&mut self, mode: ShapeMode);
+    /// Sets the amount of the shaping stage, from 0.0 (clean) to 1.0.
+    fn set_shape_amount(&mut self, amount: f32);
+    /// Sets the colour of the noise waveform, based on the [`NoiseColor`] enum.
+    fn set_noise_color(&mut self, color: NoiseColor);
+    /// Restarts the random generators of the oscillator from the given seed.
+    fn set_seed(&mut self, seed: u32);
     /// Sets the sample rate in hertz.
     fn set_sample_rate(&mut self, sample_rate: f32);
     /// Resets the internal state of the oscillator.
     fn reset(&mut self);
 }
+
+/// Trait for oscillators that can drive or follow hard sync.
+pub trait HardSync {
+    /// Where the phase wrapped during a tick, e.g. `Option<f32>` for one sample per tick.
+    type Wrap;
+
+    /// Returns where the phase wrapped during the last tick, as the fraction of a sample
+    /// elapsed between the wrap and the next sample, or `None` if it did not wrap.
+    ///
+    /// Wraps are reported in both directions, a negative phase increment wrapping backwards.
+    fn wrapped(&self) -> Self::Wrap;
+    /// Restarts the cycle during the next tick wherever the master wrapped, `None` leaving
+    /// the cycle running.
+    ///
+    /// Tick the master first, then pass its [`HardSync::wrapped`] value before ticking the slave.
+    fn hard_sync(&mut self, wrap: Self::Wrap);
+}
+
+/// Returns the fraction of a sample elapsed since the phase wrapped, given the phase advanced
+/// by `increment` from `[0.0, 1.0)`, or `None` if it did not wrap.
+///
+/// A negative increment wraps backwards through 0.0, as under through-zero FM.
+fn wrap_fraction(phase: f32, increment: f32) -> Option<f32> {
+    if phase >= 1.0 {
+        Some((phase - 1.0) / increment)
+    } else if phase < 0.0 {
+        Some(phase / increment)
+    } else {
+        None
+    }
+}
+
+/// Amplitude of the sinusoid at `frequency` in hertz, the samples spanning a whole number of its periods.
+#[cfg(test)]
+pub(crate) fn amplitude_at(samples: &[f32], frequency: f32, sample_rate: f32) -> f32 {
+    let step = std::f64::consts::TAU * frequency as f64 / sample_rate as f64;
+    let (re, im) = samples.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &sample)| {
+        let (sin, cos) = (step * n as f64).sin_cos();
+        (re + sample as f64 * cos, im - sample as f64 * sin)
+    });
+
+    (2.0 * (re * re + im * im).sqrt() / samples.len() as f64) as f32
+}
+
+/// Ratio of the energy folded back between the harmonics of `fundamental` in hertz to the
+/// energy of the harmonics, the aliases landing on multiples of `resolution` in hertz.
+///
+/// The samples must span a whole number of periods of `resolution`.
+#[cfg(test)]
+pub(crate) fn alias_ratio(samples: &[f32], fundamental: f32, resolution: f32, sample_rate: f32) -> f32 {
+    let harmonic_step = (fundamental / resolution).round() as usize;
+    let (harmonics, aliases) = (1..)
+        .map(|index| index as f32 * resolution)
+        .take_while(|&frequency| frequency < sample_rate / 2.0)
+        .enumerate()
+        .fold((0.0, 0.0), |(harmonics, aliases), (index, frequency)| {
+            let energy = amplitude_at(samples, frequency, sample_rate).powi(2);
+            if (index + 1) % harmonic_step == 0 { (harmonics + energy, aliases) } else { (harmonics, aliases + energy) }
+        });
+
+    aliases / harmonics
+}
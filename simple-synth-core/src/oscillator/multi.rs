@@ -0,0 +1,301 @@
+use std::sync::Arc;
+use super::{
+    AdditiveOscillator,
+    BasicOscillator,
+    HardSync,
+    Interpolation,
+    LoopMode,
+    NoiseColor,
+    Oscillator,
+    OscillatorQuality,
+    OscillatorType,
+    PluckExcitation,
+    PluckOscillator,
+    Sample,
+    SampleOscillator,
+    ShapeMode,
+    Waveform,
+    Wavetable,
+    WavetableOscillator,
+};
+
+/// Oscillator that renders one of the available sound sources, selected by [`OscillatorType`].
+///
+/// Every source is kept alive so switching type never allocates on the audio thread. Only the
+/// active source follows the frequency, the others catching up when they are selected.
+pub struct MultiOscillator {
+    oscillator_type: OscillatorType,
+    frequency: f32,
+    basic: BasicOscillator,
+    wavetable: WavetableOscillator,
+    pluck: PluckOscillator,
+    sample: SampleOscillator,
+    additive: AdditiveOscillator,
+}
+
+impl MultiOscillator {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            oscillator_type: OscillatorType::Basic,
+            frequency: 440.0,
+            basic: BasicOscillator::new(sample_rate),
+            wavetable: WavetableOscillator::new(sample_rate),
+            pluck: PluckOscillator::new(sample_rate),
+            sample: SampleOscillator::new(sample_rate),
+            additive: AdditiveOscillator::new(sample_rate),
+        }
+    }
+
+    pub fn oscillator_type(&self) -> OscillatorType {
+        self.oscillator_type
+    }
+
+    /// Phase of the next sample of the active source, from 0.0 to 1.0.
+    pub fn phase(&self) -> f32 {
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.phase(),
+            OscillatorType::Wavetable => self.wavetable.phase(),
+            OscillatorType::Pluck => self.pluck.phase(),
+            OscillatorType::Sample => self.sample.phase(),
+            OscillatorType::Additive => self.additive.phase(),
+        }
+    }
+
+    /// Sets the phase of the next sample of the active source, wrapped to `[0.0, 1.0)`.
+    pub fn set_phase(&mut self, phase: f32) {
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.set_phase(phase),
+            OscillatorType::Wavetable => self.wavetable.set_phase(phase),
+            OscillatorType::Pluck => self.pluck.set_phase(phase),
+            OscillatorType::Sample => self.sample.set_phase(phase),
+            OscillatorType::Additive => self.additive.set_phase(phase),
+        }
+    }
+
+    /// Phase increment per sample of the active source, negative when the phase runs backwards.
+    pub fn phase_increment(&self) -> f32 {
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.phase_increment(),
+            OscillatorType::Wavetable => self.wavetable.phase_increment(),
+            OscillatorType::Pluck => self.pluck.phase_increment(),
+            OscillatorType::Sample => self.sample.phase_increment(),
+            OscillatorType::Additive => self.additive.phase_increment(),
+        }
+    }
+
+    pub fn set_type(&mut self, oscillator_type: OscillatorType) {
+        if self.oscillator_type != oscillator_type {
+            self.oscillator_type = oscillator_type;
+            self.set_frequency(self.frequency);
+        }
+    }
+
+    pub fn set_wavetable(&mut self, table: Arc<Wavetable>) {
+        self.wavetable.set_wavetable(table);
+    }
+
+    pub fn set_wavetable_position(&mut self, position: f32) {
+        self.wavetable.set_position(position);
+    }
+
+    pub fn set_pluck_excitation(&mut self, excitation: PluckExcitation) {
+        self.pluck.set_excitation(excitation);
+    }
+
+    pub fn set_pluck_damping(&mut self, damping: f32) {
+        self.pluck.set_damping(damping);
+    }
+
+    pub fn set_pluck_brightness(&mut self, brightness: f32) {
+        self.pluck.set_brightness(brightness);
+    }
+
+    pub fn set_pluck_decay(&mut self, decay: f32) {
+        self.pluck.set_decay(decay);
+    }
+
+    /// Shares the sample with the sample source. Cloning the [`Arc`] does not allocate.
+    pub fn set_sample(&mut self, sample: Arc<Sample>) {
+        self.sample.set_sample(sample);
+    }
+
+    pub fn set_sample_interpolation(&mut self, interpolation: Interpolation) {
+        self.sample.set_interpolation(interpolation);
+    }
+
+    pub fn set_sample_loop_mode(&mut self, loop_mode: LoopMode) {
+        self.sample.set_loop_mode(loop_mode);
+    }
+
+    pub fn set_sample_loop_start(&mut self, start: f32) {
+        self.sample.set_loop_start(start);
+    }
+
+    pub fn set_sample_loop_end(&mut self, end: f32) {
+        self.sample.set_loop_end(end);
+    }
+
+    pub fn set_sample_crossfade(&mut self, crossfade: f32) {
+        self.sample.set_crossfade(crossfade);
+    }
+
+    pub fn set_sample_root_key(&mut self, root_key: u8) {
+        self.sample.set_root_key(root_key);
+    }
+
+    pub fn set_sample_fine_tune(&mut self, cents: f32) {
+        self.sample.set_fine_tune(cents);
+    }
+
+    /// Sets the partial amplitudes of the additive source, see [`AdditiveOscillator::set_amplitudes`].
+    pub fn set_additive_amplitudes(&mut self, amplitudes: &[f32]) {
+        self.additive.set_amplitudes(amplitudes);
+    }
+
+    pub fn set_additive_partials(&mut self, count: usize) {
+        self.additive.set_partial_count(count);
+    }
+
+    pub fn set_additive_brightness(&mut self, brightness: f32) {
+        self.additive.set_brightness(brightness);
+    }
+
+    pub fn set_additive_odd_even(&mut self, balance: f32) {
+        self.additive.set_odd_even(balance);
+    }
+
+    pub fn set_additive_stretch(&mut self, stretch: f32) {
+        self.additive.set_stretch(stretch);
+    }
+
+    /// Starts a note, plucking the string or restarting the sample when it is the active source.
+    pub fn trigger(&mut self) {
+        match self.oscillator_type {
+            OscillatorType::Pluck => self.pluck.pluck(),
+            OscillatorType::Sample => self.sample.trigger(),
+            _ => (),
+        }
+    }
+}
+
+impl Oscillator for MultiOscillator {
+    type Output = f32;
+
+    fn tick(&mut self) -> f32 {
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.tick(),
+            OscillatorType::Wavetable => self.wavetable.tick(),
+            OscillatorType::Pluck => self.pluck.tick(),
+            OscillatorType::Sample => self.sample.tick(),
+            OscillatorType::Additive => self.additive.tick(),
+        }
+    }
+
+    fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency;
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.set_frequency(frequency),
+            OscillatorType::Wavetable => self.wavetable.set_frequency(frequency),
+            OscillatorType::Pluck => self.pluck.set_frequency(frequency),
+            OscillatorType::Sample => self.sample.set_frequency(frequency),
+            OscillatorType::Additive => self.additive.set_frequency(frequency),
+        }
+    }
+
+    fn modulate_frequency(&mut self, offset: f32) {
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.modulate_frequency(offset),
+            OscillatorType::Wavetable => self.wavetable.modulate_frequency(offset),
+            OscillatorType::Pluck => self.pluck.modulate_frequency(offset),
+            OscillatorType::Sample => self.sample.modulate_frequency(offset),
+            OscillatorType::Additive => self.additive.modulate_frequency(offset),
+        }
+    }
+
+    fn set_waveform(&mut self, waveform: Waveform) {
+        self.basic.set_waveform(waveform);
+    }
+
+    fn set_quality(&mut self, quality: OscillatorQuality) {
+        self.basic.set_quality(quality);
+    }
+
+    fn set_pulse_width(&mut self, pulse_width: f32) {
+        self.basic.set_pulse_width(pulse_width);
+    }
+
+    fn set_shape(&mut self, mode: ShapeMode) {
+        self.basic.set_shape(mode);
+    }
+
+    fn set_shape_amount(&mut self, amount: f32) {
+        self.basic.set_shape_amount(amount);
+    }
+
+    fn set_noise_color(&mut self, color: NoiseColor) {
+        self.basic.set_noise_color(color);
+    }
+
+    fn set_seed(&mut self, seed: u32) {
+        self.basic.set_seed(seed);
+        self.pluck.set_seed(seed);
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.basic.set_sample_rate(sample_rate);
+        self.wavetable.set_sample_rate(sample_rate);
+        self.pluck.set_sample_rate(sample_rate);
+        self.sample.set_sample_rate(sample_rate);
+        self.additive.set_sample_rate(sample_rate);
+    }
+
+    fn reset(&mut self) {
+        self.basic.reset();
+        self.wavetable.reset();
+        self.pluck.reset();
+        self.sample.reset();
+        self.additive.reset();
+    }
+}
+
+impl HardSync for MultiOscillator {
+    type Wrap = Option<f32>;
+
+    fn wrapped(&self) -> Option<f32> {
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.wrapped(),
+            OscillatorType::Wavetable => self.wavetable.wrapped(),
+            OscillatorType::Pluck => self.pluck.wrapped(),
+            OscillatorType::Sample => self.sample.wrapped(),
+            OscillatorType::Additive => self.additive.wrapped(),
+        }
+    }
+
+    fn hard_sync(&mut self, wrap: Option<f32>) {
+        match self.oscillator_type {
+            OscillatorType::Basic => self.basic.hard_sync(wrap),
+            OscillatorType::Wavetable => self.wavetable.hard_sync(wrap),
+            OscillatorType::Pluck => self.pluck.hard_sync(wrap),
+            OscillatorType::Sample => self.sample.hard_sync(wrap),
+            OscillatorType::Additive => self.additive.hard_sync(wrap),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_active_source_follows_the_frequency() {
+        let mut oscillator = MultiOscillator::new(48000.0);
+        oscillator.set_frequency(4800.0);
+        assert_eq!(oscillator.basic.phase_increment(), 0.1);
+        assert_eq!(oscillator.additive.phase_increment(), 440.0 / 48000.0);
+
+        oscillator.set_type(OscillatorType::Additive);
+        assert_eq!(oscillator.additive.phase_increment(), 0.1);
+        oscillator.set_frequency(2400.0);
+        assert_eq!(oscillator.basic.phase_increment(), 0.1);
+    }
+}
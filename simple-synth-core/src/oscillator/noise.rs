@@ -0,0 +1,263 @@
+use wide::f32x4;
+use crate::rng::{Rng, RngX4};
+use super::NoiseColor;
+
+// Pink noise uses Paul Kellet's economy filter, three one-pole stages summed
+// with the white input, giving -3 dB/octave within 0.5 dB above 40 Hz.
+const PINK_POLES: [f32; 3] = [0.99765, 0.963, 0.57];
+const PINK_GAINS: [f32; 3] = [0.099046, 0.2965164, 1.0526913];
+const PINK_DIRECT: f32 = 0.1848;
+const PINK_SCALE: f32 = 0.15;
+/// Leak of the brown noise integrator, keeping it free of DC drift.
+const BROWN_LEAK: f32 = 0.02;
+const BROWN_SCALE: f32 = 4.5;
+/// Blue noise is differentiated pink noise, rising 3 dB/octave.
+const BLUE_SCALE: f32 = 2.5;
+
+/// Shapes white noise into the selected [`NoiseColor`].
+#[derive(Clone, Copy, Debug)]
+struct ColorFilter {
+    color: NoiseColor,
+    pink: [f32; 3],
+    last_pink: f32,
+    brown: f32,
+}
+
+impl ColorFilter {
+    fn new() -> Self {
+        Self {
+            color: NoiseColor::White,
+            pink: [0.0; 3],
+            last_pink: 0.0,
+            brown: 0.0,
+        }
+    }
+
+    fn process(&mut self, white: f32) -> f32 {
+        match self.color {
+            NoiseColor::White => white,
+            NoiseColor::Pink => self.pink(white),
+            NoiseColor::Brown => {
+                self.brown = (self.brown + BROWN_LEAK * white) / (1.0 + BROWN_LEAK);
+                BROWN_SCALE * self.brown
+            },
+            NoiseColor::Blue => {
+                let pink = self.pink(white);
+                let blue = pink - self.last_pink;
+                self.last_pink = pink;
+                BLUE_SCALE * blue
+            },
+        }
+    }
+
+    fn pink(&mut self, white: f32) -> f32 {
+        let mut sum = PINK_DIRECT * white;
+        for ((state, pole), gain) in self.pink.iter_mut().zip(PINK_POLES).zip(PINK_GAINS) {
+            *state = pole * *state + gain * white;
+            sum += *state;
+        }
+
+        PINK_SCALE * sum
+    }
+
+    fn reset(&mut self) {
+        self.pink = [0.0; 3];
+        self.last_pink = 0.0;
+        self.brown = 0.0;
+    }
+}
+
+/// Vectorised [`ColorFilter`] running four independent lanes.
+#[derive(Clone, Copy, Debug)]
+struct ColorFilterX4 {
+    color: NoiseColor,
+    pink: [f32x4; 3],
+    last_pink: f32x4,
+    brown: f32x4,
+}
+
+impl ColorFilterX4 {
+    fn new() -> Self {
+        Self {
+            color: NoiseColor::White,
+            pink: [f32x4::ZERO; 3],
+            last_pink: f32x4::ZERO,
+            brown: f32x4::ZERO,
+        }
+    }
+
+    fn process(&mut self, white: f32x4) -> f32x4 {
+        match self.color {
+            NoiseColor::White => white,
+            NoiseColor::Pink => self.pink(white),
+            NoiseColor::Brown => {
+                self.brown = (self.brown + f32x4::splat(BROWN_LEAK) * white) / f32x4::splat(1.0 + BROWN_LEAK);
+                f32x4::splat(BROWN_SCALE) * self.brown
+            },
+            NoiseColor::Blue => {
+                let pink = self.pink(white);
+                let blue = pink - self.last_pink;
+                self.last_pink = pink;
+                f32x4::splat(BLUE_SCALE) * blue
+            },
+        }
+    }
+
+    fn pink(&mut self, white: f32x4) -> f32x4 {
+        let mut sum = f32x4::splat(PINK_DIRECT) * white;
+        for ((state, pole), gain) in self.pink.iter_mut().zip(PINK_POLES).zip(PINK_GAINS) {
+            *state = f32x4::splat(pole) * *state + f32x4::splat(gain) * white;
+            sum += *state;
+        }
+
+        f32x4::splat(PINK_SCALE) * sum
+    }
+
+    fn reset(&mut self) {
+        self.pink = [f32x4::ZERO; 3];
+        self.last_pink = f32x4::ZERO;
+        self.brown = f32x4::ZERO;
+    }
+}
+
+/// Seedable coloured noise source producing one sample per call.
+pub struct Noise {
+    rng: Rng,
+    filter: ColorFilter,
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            filter: ColorFilter::new(),
+        }
+    }
+
+    pub fn set_color(&mut self, color: NoiseColor) {
+        if self.filter.color != color {
+            self.filter.color = color;
+            self.filter.reset();
+        }
+    }
+
+    /// Restarts the random sequence from the given seed.
+    pub fn seed(&mut self, seed: u32) {
+        self.rng.seed(seed);
+        self.filter.reset();
+    }
+
+    pub fn process(&mut self) -> f32 {
+        self.filter.process(self.rng.next_bipolar())
+    }
+}
+
+/// Seedable coloured noise source producing four samples per call.
+pub struct NoiseX4 {
+    rng: RngX4,
+    /// Filter for four independent lanes.
+    lanes: ColorFilterX4,
+    /// Filter for four consecutive samples of a single stream.
+    serial: ColorFilter,
+}
+
+impl NoiseX4 {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng: RngX4::new(seed),
+            lanes: ColorFilterX4::new(),
+            serial: ColorFilter::new(),
+        }
+    }
+
+    pub fn set_color(&mut self, color: NoiseColor) {
+        if self.lanes.color != color {
+            self.lanes.color = color;
+            self.serial.color = color;
+            self.lanes.reset();
+            self.serial.reset();
+        }
+    }
+
+    /// Restarts the random sequence of every lane from the given seed.
+    pub fn seed(&mut self, seed: u32) {
+        self.rng.seed(seed);
+        self.lanes.reset();
+        self.serial.reset();
+    }
+
+    /// Next sample of four independent noise streams.
+    pub fn process(&mut self) -> f32x4 {
+        let white = self.rng.next_bipolar();
+        self.lanes.process(white)
+    }
+
+    /// Next four consecutive samples of a single noise stream.
+    pub fn process_serial(&mut self) -> f32x4 {
+        let white = self.rng.next_bipolar();
+        if self.serial.color == NoiseColor::White {
+            return white;
+        }
+
+        f32x4::from(white.to_array().map(|sample| self.serial.process(sample)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oscillator::amplitude_at;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Length of the segments whose spectra are averaged, giving bins 46.875 Hz apart.
+    const SEGMENT: usize = 1024;
+
+    /// Average power of the noise between `low` and twice `low` hertz, in decibels,
+    /// averaged over the bins of every Hann-windowed segment.
+    fn octave_power(samples: &[f32], low: f32) -> f32 {
+        let resolution = SAMPLE_RATE / SEGMENT as f32;
+        let bins = (low / resolution).ceil() as usize..(2.0 * low / resolution).ceil() as usize;
+        let windowed: Vec<f32> = samples
+            .iter()
+            .enumerate()
+            .map(|(n, sample)| sample * (std::f32::consts::PI * (n % SEGMENT) as f32 / SEGMENT as f32).sin().powi(2))
+            .collect();
+        let (power, count) = windowed
+            .chunks_exact(SEGMENT)
+            .flat_map(|segment| bins.clone().map(move |bin| (segment, bin as f32 * resolution)))
+            .fold((0.0, 0), |(power, count), (segment, frequency)| {
+                (power + amplitude_at(segment, frequency, SAMPLE_RATE).powi(2), count + 1)
+            });
+
+        10.0 * (power / count as f32).log10()
+    }
+
+    /// Slope of the noise spectrum in decibels per octave, measured between the octaves
+    /// from 1 kHz and from 4 kHz.
+    fn slope(color: NoiseColor) -> f32 {
+        let mut noise = Noise::new(1);
+        noise.set_color(color);
+        let samples: Vec<f32> = (0..256 * SEGMENT).map(|_| noise.process()).collect();
+
+        (octave_power(&samples, 4000.0) - octave_power(&samples, 1000.0)) / 2.0
+    }
+
+    #[test]
+    fn white_noise_is_flat() {
+        let slope = slope(NoiseColor::White);
+        assert!(slope.abs() < 0.5, "{slope} dB/octave");
+    }
+
+    #[test]
+    fn pink_noise_falls_3_db_per_octave() {
+        let slope = slope(NoiseColor::Pink);
+        assert!((slope + 3.0).abs() < 0.5, "{slope} dB/octave");
+    }
+
+    #[test]
+    fn brown_noise_falls_6_db_per_octave() {
+        let slope = slope(NoiseColor::Brown);
+        assert!((slope + 6.0).abs() < 0.5, "{slope} dB/octave");
+    }
+}
@@ -0,0 +1,266 @@
+use crate::rng::Rng;
+use super::{wrap_fraction, HardSync, NoiseColor, Oscillator, OscillatorQuality, PluckExcitation, ShapeMode, Waveform};
+
+/// Lowest tuned frequency, sizing the delay line.
+const MIN_FREQUENCY: f32 = 20.0;
+/// Smallest fractional delay handled by the allpass, keeping its coefficient away from -1.
+const MIN_FRACTION: f32 = 0.1;
+
+/// Karplus-Strong plucked string.
+///
+/// The excitation circulates in a delay line through a damping lowpass, a fractional delay
+/// allpass tuning the loop to the exact period, and a gain setting the decay time.
+/// A phantom phase runs at the string frequency so the string can drive hard sync and
+/// the sub-oscillator.
+pub struct PluckOscillator {
+    buffer: Vec<f32>,
+    write: usize,
+    /// Integer part of the loop delay in samples.
+    delay: usize,
+    /// Fractional delay allpass coefficient.
+    allpass_coefficient: f32,
+    allpass_input: f32,
+    allpass_output: f32,
+    /// Previous delay line output, used by the damping lowpass.
+    last_delayed: f32,
+    loop_gain: f32,
+    excitation: PluckExcitation,
+    /// Weight of the previous sample in the damping lowpass, from 0.0 to 0.5.
+    damping: f32,
+    brightness: f32,
+    /// Time for the fundamental to decay by 60 dB, in seconds.
+    decay: f32,
+    phase: f32,
+    wrap_fraction: Option<f32>,
+    sample_rate: f32,
+    frequency: f32,
+    /// Frequency offset in hertz applied to the next tick.
+    frequency_offset: f32,
+    rng: Rng,
+}
+
+impl PluckOscillator {
+    pub fn new(sample_rate: f32) -> Self {
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+
+        let mut oscillator = Self {
+            buffer: vec![0.0; buffer_len(sample_rate)],
+            write: 0,
+            delay: 1,
+            allpass_coefficient: 0.0,
+            allpass_input: 0.0,
+            allpass_output: 0.0,
+            last_delayed: 0.0,
+            loop_gain: 0.0,
+            excitation: PluckExcitation::Noise,
+            damping: 0.25,
+            brightness: 1.0,
+            decay: 2.0,
+            phase: 0.0,
+            wrap_fraction: None,
+            sample_rate,
+            frequency: 440.0,
+            frequency_offset: 0.0,
+            rng: Rng::new(0),
+        };
+        oscillator.tune(oscillator.frequency);
+
+        oscillator
+    }
+
+    pub fn set_excitation(&mut self, excitation: PluckExcitation) {
+        if self.excitation != excitation {
+            self.excitation = excitation;
+        }
+    }
+
+    /// Sets the high-frequency loss of the string, from 0.0 (bright, long ring) to 1.0 (dull).
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = 0.5 * damping.clamp(0.0, 1.0);
+        self.tune(self.frequency);
+    }
+
+    /// Sets the tone of the excitation, from 0.0 (soft) to 1.0 (unfiltered).
+    pub fn set_brightness(&mut self, brightness: f32) {
+        self.brightness = brightness.clamp(0.0, 1.0);
+    }
+
+    /// Sets the time for the fundamental to decay by 60 dB, in seconds.
+    pub fn set_decay(&mut self, decay: f32) {
+        self.decay = decay.clamp(0.01, 30.0);
+        self.tune(self.frequency);
+    }
+
+    /// Phase of the next sample, from 0.0 to 1.0.
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Sets the phase of the next sample, wrapped to `[0.0, 1.0)`.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = phase.rem_euclid(1.0);
+    }
+
+    /// Phase increment per sample, negative when the phase runs backwards.
+    pub fn phase_increment(&self) -> f32 {
+        ((self.frequency + self.frequency_offset) / self.sample_rate).clamp(-0.5, 0.5)
+    }
+
+    /// Fills the string with a new excitation, one period long.
+    pub fn pluck(&mut self) {
+        self.tune(self.frequency);
+        let period = self.delay;
+        let len = self.buffer.len();
+
+        // Key-tracked lowpass for the filtered noise, around the fourth harmonic.
+        let tracking = 1.0 - (-2.0 * std::f32::consts::PI * 4.0 * self.frequency / self.sample_rate).exp();
+        let brightness = self.brightness * self.brightness;
+        let mut tracked = 0.0;
+        let mut toned = 0.0;
+        let mut sum = 0.0;
+
+        for i in 0..period {
+            let sample = match self.excitation {
+                PluckExcitation::Noise => self.rng.next_bipolar(),
+                PluckExcitation::FilteredNoise => {
+                    tracked += tracking * (self.rng.next_bipolar() - tracked);
+                    // Restores the level lost by the lowpass.
+                    tracked * ((2.0 - tracking) / tracking).sqrt()
+                },
+                PluckExcitation::Impulse => 2.0 * i as f32 / period as f32 - 1.0,
+            };
+            toned += brightness.max(0.01) * (sample - toned);
+
+            self.buffer[(self.write + len - period + i) % len] = toned;
+            sum += toned;
+        }
+
+        // Removes the DC offset, which would otherwise circulate forever.
+        let mean = sum / period as f32;
+        for i in 0..period {
+            self.buffer[(self.write + len - period + i) % len] -= mean;
+        }
+
+        self.allpass_input = 0.0;
+        self.allpass_output = 0.0;
+        self.last_delayed = 0.0;
+    }
+
+    /// Sets the loop delay and gain for the given frequency.
+    fn tune(&mut self, frequency: f32) {
+        let frequency = frequency.clamp(MIN_FREQUENCY, self.sample_rate / 4.0);
+        let period = self.sample_rate / frequency;
+
+        // The damping lowpass delays the loop by its weight, the allpass adds the rest.
+        let delay = period - self.damping;
+        let whole = (delay - MIN_FRACTION).floor().max(1.0);
+        let fraction = delay - whole;
+        self.delay = (whole as usize).min(self.buffer.len() - 1);
+        self.allpass_coefficient = (1.0 - fraction) / (1.0 + fraction);
+
+        // The decay time is set for the fundamental, compensating the lowpass loss.
+        let omega = 2.0 * std::f32::consts::PI * frequency / self.sample_rate;
+        let lowpass_gain = ((1.0 - self.damping).powi(2) + self.damping.powi(2)
+            + 2.0 * self.damping * (1.0 - self.damping) * omega.cos()).sqrt();
+        let gain = 10.0_f32.powf(-3.0 / (frequency * self.decay));
+        self.loop_gain = (gain / lowpass_gain).min(0.99999);
+    }
+}
+
+/// Delay line length holding one period of [`MIN_FREQUENCY`].
+fn buffer_len(sample_rate: f32) -> usize {
+    (sample_rate / MIN_FREQUENCY).ceil() as usize + 2
+}
+
+impl Oscillator for PluckOscillator {
+    type Output = f32;
+
+    fn tick(&mut self) -> f32 {
+        let offset = self.frequency_offset != 0.0;
+        if offset {
+            self.tune(self.frequency + self.frequency_offset);
+        }
+
+        let len = self.buffer.len();
+        let delayed = self.buffer[(self.write + len - self.delay) % len];
+        let filtered = (1.0 - self.damping) * delayed + self.damping * self.last_delayed;
+        self.last_delayed = delayed;
+
+        let tuned = self.allpass_coefficient * (filtered - self.allpass_output) + self.allpass_input;
+        self.allpass_input = filtered;
+        self.allpass_output = tuned;
+
+        self.buffer[self.write] = tuned * self.loop_gain;
+        self.write = (self.write + 1) % len;
+
+        let increment = self.phase_increment();
+        let phase = self.phase + increment;
+        self.wrap_fraction = wrap_fraction(phase, increment);
+        self.phase = phase.rem_euclid(1.0);
+
+        if offset {
+            self.frequency_offset = 0.0;
+            self.tune(self.frequency);
+        }
+
+        delayed
+    }
+
+    fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.clamp(0.0, self.sample_rate / 2.0);
+        self.tune(self.frequency);
+    }
+
+    fn modulate_frequency(&mut self, offset: f32) {
+        self.frequency_offset = offset;
+    }
+
+    fn set_waveform(&mut self, _waveform: Waveform) {}
+
+    fn set_quality(&mut self, _quality: OscillatorQuality) {}
+
+    fn set_pulse_width(&mut self, _pulse_width: f32) {}
+
+    fn set_shape(&mut self, _mode: ShapeMode) {}
+
+    fn set_shape_amount(&mut self, _amount: f32) {}
+
+    fn set_noise_color(&mut self, _color: NoiseColor) {}
+
+    fn set_seed(&mut self, seed: u32) {
+        self.rng.seed(seed);
+    }
+
+    /// Grows the delay line when the sample rate increases, which allocates.
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 {
+            self.sample_rate = sample_rate;
+            let len = buffer_len(sample_rate);
+            if len > self.buffer.len() {
+                self.buffer.resize(len, 0.0);
+            }
+            self.tune(self.frequency);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write = 0;
+        self.allpass_input = 0.0;
+        self.allpass_output = 0.0;
+        self.last_delayed = 0.0;
+        self.phase = 0.0;
+        self.wrap_fraction = None;
+    }
+}
+
+impl HardSync for PluckOscillator {
+    type Wrap = Option<f32>;
+
+    fn wrapped(&self) -> Option<f32> {
+        self.wrap_fraction
+    }
+
+    /// A string cannot restart its cycle, so it does not follow hard sync.
+    fn hard_sync(&mut self, _wrap: Option<f32>) {}
+}
@@ -0,0 +1,419 @@
+use std::{path::Path, sync::Arc};
+use crate::wav::{WavError, WavFile};
+use super::{wrap_fraction, HardSync, Interpolation, LoopMode, NoiseColor, Oscillator, OscillatorQuality, ShapeMode, Waveform};
+
+/// Shortest loop in frames, leaving room for the interpolation to read across the loop points.
+const MIN_LOOP_LEN: f64 = 4.0;
+
+/// A mono recording with the key it was played at and an optional loop.
+pub struct Sample {
+    data: Vec<f32>,
+    sample_rate: f32,
+    root_key: u8,
+    /// Loop start and end frames, the end being exclusive.
+    loop_points: Option<(usize, usize)>,
+}
+
+impl Sample {
+    /// Builds a sample from mono frames recorded at the given sample rate, rooted at C4 without a loop.
+    pub fn new(data: Vec<f32>, sample_rate: f32) -> Self {
+        assert!(!data.is_empty(), "Sample must contain at least one frame.");
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+
+        Self {
+            data,
+            sample_rate,
+            root_key: 60,
+            loop_points: None,
+        }
+    }
+
+    /// Loads a sample from a WAV file on disk, see [`Sample::from_wav`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WavError> {
+        Self::from_wav(&WavFile::open(path)?)
+    }
+
+    /// Builds a sample from a decoded WAV file, mixed down to mono.
+    ///
+    /// The root key and the first loop come from the `smpl` chunk when present.
+    pub fn from_wav(wav: &WavFile) -> Result<Self, WavError> {
+        if wav.sample_rate == 0 {
+            return Err(WavError::InvalidLayout);
+        }
+        let data = wav.to_mono();
+        if data.is_empty() {
+            return Err(WavError::Empty);
+        }
+
+        let mut sample = Self::new(data, wav.sample_rate as f32);
+        if let Some(root_key) = wav.root_key {
+            sample.root_key = root_key;
+        }
+        sample.loop_points = wav.loop_points.filter(|&(start, end)| start < end && end <= sample.data.len());
+
+        Ok(sample)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// MIDI note played back at the recorded pitch.
+    pub fn root_key(&self) -> u8 {
+        self.root_key
+    }
+
+    /// Loop start and end frames stored with the recording, the end being exclusive.
+    pub fn loop_points(&self) -> Option<(usize, usize)> {
+        self.loop_points
+    }
+}
+
+/// Plays a [`Sample`] pitched to the note by resampling.
+///
+/// Playback starts over on [`SampleOscillator::trigger`] and runs through the optional
+/// loop until the note ends. Resampling is not band-limited, so samples pitched far
+/// above their root key alias.
+pub struct SampleOscillator {
+    sample: Option<Arc<Sample>>,
+    interpolation: Interpolation,
+    loop_mode: LoopMode,
+    /// Loop start as a fraction of the sample length.
+    loop_start: f32,
+    /// Loop end as a fraction of the sample length.
+    loop_end: f32,
+    /// Forward loop crossfade as a fraction of the loop length, from 0.0 to 0.5.
+    crossfade: f32,
+    root_key: u8,
+    /// Tuning offset in cents.
+    fine_tune: f32,
+    /// Read position in frames of the sample.
+    position: f64,
+    /// Whether ping-pong playback is running backwards.
+    reversed: bool,
+    /// Whether playback reached the loop start since the last trigger.
+    looping: bool,
+    /// Frames read per output sample and per hertz of the played frequency.
+    speed: f64,
+    phase: f32,
+    wrap_fraction: Option<f32>,
+    sync_fraction: Option<f32>,
+    sample_rate: f32,
+    frequency: f32,
+    /// Frequency offset in hertz applied to the next tick.
+    frequency_offset: f32,
+}
+
+impl SampleOscillator {
+    pub fn new(sample_rate: f32) -> Self {
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+
+        Self {
+            sample: None,
+            interpolation: Interpolation::Hermite,
+            loop_mode: LoopMode::Off,
+            loop_start: 0.0,
+            loop_end: 1.0,
+            crossfade: 0.0,
+            root_key: 60,
+            fine_tune: 0.0,
+            position: 0.0,
+            reversed: false,
+            looping: false,
+            speed: 0.0,
+            phase: 0.0,
+            wrap_fraction: None,
+            sync_fraction: None,
+            sample_rate,
+            frequency: 440.0,
+            frequency_offset: 0.0,
+        }
+    }
+
+    /// Replaces the sample, keeping the loop points and root key set on the oscillator; the sample
+    /// is shared, not copied.
+    ///
+    /// The root key and loop stored with the recording are only suggestions, see
+    /// [`Sample::root_key`] and [`Sample::loop_points`].
+    pub fn set_sample(&mut self, sample: Arc<Sample>) {
+        self.sample = Some(sample);
+        self.update_speed();
+        self.trigger();
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        if self.interpolation != interpolation {
+            self.interpolation = interpolation;
+        }
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        if self.loop_mode != loop_mode {
+            self.loop_mode = loop_mode;
+        }
+    }
+
+    /// Sets the loop start as a fraction of the sample length, from 0.0 to 1.0.
+    pub fn set_loop_start(&mut self, start: f32) {
+        self.loop_start = start.clamp(0.0, 1.0);
+    }
+
+    /// Sets the loop end as a fraction of the sample length, from 0.0 to 1.0.
+    pub fn set_loop_end(&mut self, end: f32) {
+        self.loop_end = end.clamp(0.0, 1.0);
+    }
+
+    /// Sets the forward loop crossfade as a fraction of the loop length, from 0.0 to 0.5.
+    ///
+    /// The fade is also limited by the audio available before the loop start.
+    pub fn set_crossfade(&mut self, crossfade: f32) {
+        self.crossfade = crossfade.clamp(0.0, 0.5);
+    }
+
+    /// Sets the MIDI note at which the sample plays at its recorded pitch.
+    pub fn set_root_key(&mut self, root_key: u8) {
+        self.root_key = root_key.min(127);
+        self.update_speed();
+    }
+
+    /// Sets the tuning offset in cents, raising the played pitch when positive.
+    pub fn set_fine_tune(&mut self, cents: f32) {
+        self.fine_tune = cents.clamp(-100.0, 100.0);
+        self.update_speed();
+    }
+
+    /// Starts playback over from the first frame.
+    pub fn trigger(&mut self) {
+        self.position = 0.0;
+        self.reversed = false;
+        self.looping = false;
+    }
+
+    /// Phase of the next sample, from 0.0 to 1.0.
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Sets the phase of the next sample, wrapped to `[0.0, 1.0)`.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = phase.rem_euclid(1.0);
+    }
+
+    /// Phase increment per sample, negative when the phase runs backwards.
+    pub fn phase_increment(&self) -> f32 {
+        ((self.frequency + self.frequency_offset) / self.sample_rate).clamp(-0.5, 0.5)
+    }
+
+    fn update_speed(&mut self) {
+        self.speed = match &self.sample {
+            Some(sample) => {
+                let root_note = self.root_key as f64 - self.fine_tune as f64 / 100.0;
+                let root_frequency = 440.0 * 2.0_f64.powf((root_note - 69.0) / 12.0);
+                sample.sample_rate() as f64 / (self.sample_rate as f64 * root_frequency)
+            },
+            None => 0.0,
+        };
+    }
+
+    /// Loop start and end frames for a sample of the given length, or `None` when not looping.
+    fn loop_frames(&self, len: usize) -> Option<(f64, f64)> {
+        if self.loop_mode == LoopMode::Off {
+            return None;
+        }
+
+        let len = len as f64;
+        let end = (self.loop_end as f64 * len).floor();
+        let start = (self.loop_start as f64 * len).floor().min(end - MIN_LOOP_LEN);
+
+        (start >= 0.0).then_some((start, end))
+    }
+
+    fn render(&self, data: &[f32]) -> f32 {
+        let frames = self.loop_frames(data.len());
+        if frames.is_none() && self.position >= (data.len() - 1) as f64 {
+            return 0.0;
+        }
+
+        let sample = self.read(data, self.position, frames);
+        if let (LoopMode::Forward, Some((start, end))) = (self.loop_mode, frames) {
+            // Fades towards the audio leading into the loop start, which the loop jumps back to.
+            let fade = (self.crossfade as f64 * (end - start)).min(start);
+            let fade_start = end - fade;
+            if fade > 0.0 && self.position >= fade_start {
+                let amount = ((self.position - fade_start) / fade) as f32;
+                let target = self.read(data, self.position - (end - start), frames);
+                return sample + (target - sample) * amount;
+            }
+        }
+
+        sample
+    }
+
+    /// Reads the sample at a fractional frame position, continuing across the loop points.
+    fn read(&self, data: &[f32], position: f64, frames: Option<(f64, f64)>) -> f32 {
+        let index = position.floor();
+        let fraction = (position - index) as f32;
+        let index = index as i64;
+
+        let frame = |i: i64| -> f32 {
+            let i = match (self.loop_mode, frames) {
+                (LoopMode::Forward, Some((start, end))) if i >= end as i64 => i - (end - start) as i64,
+                (LoopMode::PingPong, Some((start, end))) => {
+                    let last = end as i64 - 1;
+                    if i > last {
+                        2 * last - i
+                    } else if self.looping && i < start as i64 {
+                        2 * start as i64 - i
+                    } else {
+                        i
+                    }
+                },
+                _ => i,
+            };
+            data[i.clamp(0, data.len() as i64 - 1) as usize]
+        };
+
+        match self.interpolation {
+            Interpolation::Linear => {
+                let current = frame(index);
+                current + (frame(index + 1) - current) * fraction
+            },
+            Interpolation::Hermite => hermite(
+                frame(index - 1),
+                frame(index),
+                frame(index + 1),
+                frame(index + 2),
+                fraction,
+            ),
+        }
+    }
+
+    /// Moves the read position by the given number of frames, following the loop.
+    fn advance(&mut self, frames: f64, len: usize) {
+        let Some((start, end)) = self.loop_frames(len) else {
+            self.position = (self.position + frames).min(len as f64);
+            return;
+        };
+
+        match self.loop_mode {
+            LoopMode::PingPong => {
+                // Unfolds the back and forth travel into one forward cycle of twice the loop length.
+                let span = end - 1.0 - start;
+                let offset = if self.reversed {
+                    2.0 * span - (self.position - start)
+                } else {
+                    self.position - start
+                };
+                let offset = offset + frames;
+                self.looping |= offset >= 0.0;
+                if self.looping {
+                    let offset = offset.rem_euclid(2.0 * span);
+                    self.reversed = offset > span;
+                    self.position = if self.reversed { start + 2.0 * span - offset } else { start + offset };
+                } else {
+                    self.position = start + offset;
+                }
+            },
+            _ => {
+                self.position += frames;
+                self.looping |= self.position >= start;
+                if self.position >= end {
+                    self.position = start + (self.position - start).rem_euclid(end - start);
+                }
+            },
+        }
+    }
+}
+
+/// Cubic Hermite interpolation between `y0` and `y1`, with `y_1` and `y2` their outer neighbours.
+fn hermite(y_1: f32, y0: f32, y1: f32, y2: f32, x: f32) -> f32 {
+    let c1 = 0.5 * (y1 - y_1);
+    let c2 = y_1 - 2.5 * y0 + 2.0 * y1 - 0.5 * y2;
+    let c3 = 0.5 * (y2 - y_1) + 1.5 * (y0 - y1);
+
+    ((c3 * x + c2) * x + c1) * x + y0
+}
+
+impl Oscillator for SampleOscillator {
+    type Output = f32;
+
+    fn tick(&mut self) -> f32 {
+        let (sample, len) = match self.sample.as_deref() {
+            Some(sample) => (self.render(&sample.data), sample.frame_count()),
+            None => (0.0, 0),
+        };
+
+        // The sample only plays forwards, FM below 0 Hz holds it still.
+        let frames = ((self.frequency + self.frequency_offset).max(0.0) as f64) * self.speed;
+        let increment = self.phase_increment();
+        if let Some(fraction) = self.sync_fraction.take() {
+            self.trigger();
+            self.advance(fraction as f64 * frames, len);
+            self.phase = (fraction * increment).rem_euclid(1.0);
+            self.wrap_fraction = Some(fraction);
+        } else {
+            self.advance(frames, len);
+            let phase = self.phase + increment;
+            self.wrap_fraction = wrap_fraction(phase, increment);
+            self.phase = phase.rem_euclid(1.0);
+        }
+        self.frequency_offset = 0.0;
+
+        sample
+    }
+
+    fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.clamp(0.0, self.sample_rate / 2.0);
+    }
+
+    fn modulate_frequency(&mut self, offset: f32) {
+        self.frequency_offset = offset;
+    }
+
+    /// The sound comes from the sample, so the waveform is ignored.
+    fn set_waveform(&mut self, _waveform: Waveform) {}
+
+    /// The quality is set by the interpolation, see [`SampleOscillator::set_interpolation`].
+    fn set_quality(&mut self, _quality: OscillatorQuality) {}
+
+    fn set_pulse_width(&mut self, _pulse_width: f32) {}
+
+    fn set_shape(&mut self, _mode: ShapeMode) {}
+
+    fn set_shape_amount(&mut self, _amount: f32) {}
+
+    fn set_noise_color(&mut self, _color: NoiseColor) {}
+
+    fn set_seed(&mut self, _seed: u32) {}
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 {
+            self.sample_rate = sample_rate;
+            self.update_speed();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.trigger();
+        self.phase = 0.0;
+        self.wrap_fraction = None;
+        self.sync_fraction = None;
+    }
+}
+
+/// Hard sync restarts the sample from its first frame.
+impl HardSync for SampleOscillator {
+    type Wrap = Option<f32>;
+
+    fn wrapped(&self) -> Option<f32> {
+        self.wrap_fraction
+    }
+
+    fn hard_sync(&mut self, wrap: Option<f32>) {
+        self.sync_fraction = wrap.map(|fraction| fraction.clamp(0.0, 1.0));
+    }
+}
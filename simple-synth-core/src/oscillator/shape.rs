@@ -0,0 +1,80 @@
+use wide::{f32x4, CmpLt};
+
+/// Knee of the phase distortion at full amount, kept above 0.0 so the warp stays finite.
+const MIN_KNEE: f32 = 0.02;
+/// Gain in front of the wavefolder at full amount.
+const MAX_FOLD_GAIN: f32 = 8.0;
+/// Gain in front of the waveshaper at full amount.
+const MAX_DRIVE: f32 = 10.0;
+/// Drive below which the waveshaper passes the signal through.
+const MIN_DRIVE: f32 = 1e-3;
+
+/// Casio-style phase distortion, squeezing the first half of the cycle into `[0.0, knee)`.
+///
+/// Turns a sine into a resonant sawtooth-like shape as `amount` goes from 0.0 to 1.0.
+pub fn phase_distortion(phase: f32, amount: f32) -> f32 {
+    let knee = 0.5 - (0.5 - MIN_KNEE) * amount;
+    if phase < knee {
+        0.5 * phase / knee
+    } else {
+        0.5 + 0.5 * (phase - knee) / (1.0 - knee)
+    }
+}
+
+/// Triangle wavefolder, reflecting the amplified signal back into `[-1.0, 1.0]`.
+///
+/// An `amount` of 0.0 leaves signals within `[-1.0, 1.0]` untouched.
+pub fn wavefold(sample: f32, amount: f32) -> f32 {
+    let x = 0.25 * (sample * (1.0 + (MAX_FOLD_GAIN - 1.0) * amount) + 1.0);
+    1.0 - 4.0 * (x - x.floor() - 0.5).abs()
+}
+
+/// Soft-clipping waveshaper, normalized so a full-scale input keeps its peak.
+///
+/// An `amount` of 0.0 passes the signal through.
+pub fn waveshape(sample: f32, amount: f32) -> f32 {
+    let drive = MAX_DRIVE * amount;
+    if drive < MIN_DRIVE {
+        return sample;
+    }
+
+    soft_clip(drive * sample) / soft_clip(drive)
+}
+
+/// Padé approximation of `tanh`, clipping at ±3.0 where it reaches ±1.0.
+fn soft_clip(x: f32) -> f32 {
+    let x = x.clamp(-3.0, 3.0);
+    x * (27.0 + x * x) / (27.0 + 9.0 * x * x)
+}
+
+/// Four-lane version of [`phase_distortion`], with an amount per lane.
+pub fn phase_distortion_x4(phase: f32x4, amount: f32x4) -> f32x4 {
+    let knee = f32x4::splat(0.5) - f32x4::splat(0.5 - MIN_KNEE) * amount;
+    let half = f32x4::splat(0.5);
+
+    phase.cmp_lt(knee).blend(
+        half * phase / knee,
+        half + half * (phase - knee) / (f32x4::ONE - knee),
+    )
+}
+
+/// Four-lane version of [`wavefold`], with an amount per lane.
+pub fn wavefold_x4(sample: f32x4, amount: f32x4) -> f32x4 {
+    let gain = f32x4::ONE + f32x4::splat(MAX_FOLD_GAIN - 1.0) * amount;
+    let x = f32x4::splat(0.25) * (sample * gain + f32x4::ONE);
+    f32x4::ONE - f32x4::splat(4.0) * (x - x.floor() - f32x4::splat(0.5)).abs()
+}
+
+/// Four-lane version of [`waveshape`], with an amount per lane.
+pub fn waveshape_x4(sample: f32x4, amount: f32x4) -> f32x4 {
+    let drive = f32x4::splat(MAX_DRIVE) * amount;
+    let shaped = soft_clip_x4(drive * sample) / soft_clip_x4(drive);
+
+    drive.cmp_lt(MIN_DRIVE).blend(sample, shaped)
+}
+
+/// Four-lane version of [`soft_clip`].
+fn soft_clip_x4(x: f32x4) -> f32x4 {
+    let x = x.max(f32x4::splat(-3.0)).min(f32x4::splat(3.0));
+    x * (f32x4::splat(27.0) + x * x) / (f32x4::splat(27.0) + f32x4::splat(9.0) * x * x)
+}
@@ -1,90 +1,382 @@
 use std::f32::consts::PI;
-use rand::random;
 use wide::{f32x4, CmpLt};
-use super::{Oscillator, Waveform};
+use super::{
+    basic::{naive_sawtooth, naive_square, naive_triangle, sync_residuals},
+    blep::{poly_blamp_x4, poly_blep_x4},
+    shape::{phase_distortion, phase_distortion_x4, wavefold_x4, waveshape_x4},
+    wrap_fraction,
+    HardSync,
+    NoiseColor,
+    NoiseX4,
+    Oscillator,
+    OscillatorQuality,
+    ShapeMode,
+    Waveform,
+};
+
+/// Describes what the four lanes of a [`SimdOscillator`] represent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LaneLayout {
+    /// Four consecutive samples of a single oscillator.
+    Time,
+    /// Four independent oscillators, each with its own frequency ratio.
+    Parallel,
+}
 
 pub struct SimdOscillator {
+    /// Phase of each lane, only lane 0 holding the phase of the next sample between ticks in
+    /// the time layout.
     phase: f32x4,
-    step: f32,
+    /// Phase increment per sample of each lane.
+    step: f32x4,
+    /// Frequency ratio of each lane in the parallel layout.
+    ratios: f32x4,
+    layout: LaneLayout,
     sample_rate: f32,
     frequency: f32,
+    /// Frequency offset in hertz applied to the next tick.
+    frequency_offset: f32,
     waveform: Waveform,
+    quality: OscillatorQuality,
+    /// Pulse width of each lane.
+    pulse_width: f32x4,
+    shape: ShapeMode,
+    /// Shape amount of each lane.
+    shape_amount: f32x4,
+    noise: NoiseX4,
+    /// Fraction of a sample since the phase wrapped in each lane of the last tick.
+    wrap_fractions: [Option<f32>; 4],
+    /// Pending hard syncs of the lanes of the next tick requested by a master oscillator.
+    sync_fractions: [Option<f32>; 4],
+    /// Band-limiting residual of a hard sync in the last sample, added to the next sample.
+    sync_residual: f32,
 }
 
 impl SimdOscillator {
+    /// Creates an oscillator producing four consecutive samples per [`tick`](Oscillator::tick).
     pub fn new(sample_rate: f32) -> Self {
-        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+        Self::with_layout(sample_rate, LaneLayout::Time)
+    }
 
-        let frequency = 440.0;
-        let step = frequency / sample_rate;
+    /// Creates an oscillator running four independent lanes, one sample each per
+    /// [`tick`](Oscillator::tick), e.g. for unison stacks.
+    pub fn parallel(sample_rate: f32) -> Self {
+        Self::with_layout(sample_rate, LaneLayout::Parallel)
+    }
+
+    fn with_layout(sample_rate: f32, layout: LaneLayout) -> Self {
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
 
-        Self {
-            phase: f32x4::from([0.0, step, 2.0 * step, 3.0 * step]),
-            step,
+        let mut oscillator = Self {
+            phase: f32x4::ZERO,
+            step: f32x4::ZERO,
+            ratios: f32x4::ONE,
+            layout,
             sample_rate,
-            frequency,
+            frequency: 440.0,
+            frequency_offset: 0.0,
             waveform: Waveform::Sine,
+            quality: OscillatorQuality::BandLimited,
+            pulse_width: f32x4::splat(0.5),
+            shape: ShapeMode::Off,
+            shape_amount: f32x4::ZERO,
+            noise: NoiseX4::new(0),
+            wrap_fractions: [None; 4],
+            sync_fractions: [None; 4],
+            sync_residual: 0.0,
+        };
+        oscillator.reset();
+
+        oscillator
+    }
+
+    /// Sets the frequency ratio of each lane relative to the oscillator frequency.
+    ///
+    /// Only used by the parallel layout.
+    pub fn set_lane_ratios(&mut self, ratios: [f32; 4]) {
+        self.ratios = f32x4::from(ratios);
+        self.update_step();
+    }
+
+    /// Sets the phase of each lane, wrapped to `[0.0, 1.0)`.
+    ///
+    /// Only used by the parallel layout.
+    pub fn set_lane_phases(&mut self, phases: [f32; 4]) {
+        if self.layout == LaneLayout::Parallel {
+            let phase = f32x4::from(phases);
+            self.phase = phase - phase.floor();
+        }
+    }
+
+    /// Sets the frequency of each lane in hertz, replacing the oscillator frequency and lane ratios.
+    ///
+    /// Only used by the parallel layout.
+    pub fn set_lane_frequencies(&mut self, frequencies: [f32; 4]) {
+        self.frequency = 1.0;
+        self.set_lane_ratios(frequencies);
+    }
+
+    /// Phase of the next sample of each lane, from 0.0 to 1.0.
+    pub fn lane_phases(&self) -> [f32; 4] {
+        self.phase.to_array()
+    }
+
+    /// Phase of the next sample of lane 0, from 0.0 to 1.0.
+    pub fn phase(&self) -> f32 {
+        self.phase.to_array()[0]
+    }
+
+    /// Sets the phase of the next sample, wrapped to `[0.0, 1.0)`.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = f32x4::splat(phase.rem_euclid(1.0));
+    }
+
+    pub fn waveform(&self) -> Waveform {
+        self.waveform
+    }
+
+    /// Sets the pulse width of each lane, or of each of the next four samples in the time layout.
+    pub fn set_pulse_width_x4(&mut self, pulse_width: f32x4) {
+        self.pulse_width = pulse_width.max(f32x4::splat(0.01)).min(f32x4::splat(0.99));
+    }
+
+    /// Sets the shape amount of each lane, or of each of the next four samples in the time layout.
+    pub fn set_shape_amount_x4(&mut self, amount: f32x4) {
+        self.shape_amount = amount.max(f32x4::ZERO).min(f32x4::ONE);
+    }
+
+    /// Generates the next `samples` samples of the time layout, at most four, and advances the
+    /// phase by as many, the lanes past `samples` holding the samples that would follow.
+    ///
+    /// The parallel layout always advances by one sample, as [`tick`](Oscillator::tick) does.
+    pub fn tick_samples(&mut self, samples: usize) -> f32x4 {
+        assert!((1..=4).contains(&samples), "A tick renders one to four samples.");
+
+        let step = if self.frequency_offset != 0.0 {
+            self.step_for(self.frequency + self.frequency_offset)
+        } else {
+            self.step
+        };
+        let (sample, next_phase) = match self.layout {
+            LaneLayout::Time => {
+                let (next_phase, residuals) = self.advance_time_lanes(samples, step.to_array()[0]);
+                let sample = match residuals {
+                    Some([carried, synced]) => self.generate(step) + carried + synced,
+                    None => self.generate(step),
+                };
+                (sample, Some(next_phase))
+            },
+            LaneLayout::Parallel => (self.generate(step), None),
+        };
+        let sample = match self.shape {
+            ShapeMode::Wavefolder => wavefold_x4(sample, self.shape_amount),
+            ShapeMode::Waveshaper => waveshape_x4(sample, self.shape_amount),
+            ShapeMode::Off | ShapeMode::PhaseDistortion => sample,
+        };
+
+        self.phase = match next_phase {
+            Some(phase) => f32x4::splat(phase),
+            None => {
+                let phase = self.phase + step;
+                phase - phase.floor()
+            },
+        };
+        self.frequency_offset = 0.0;
+
+        sample
+    }
+
+    fn update_step(&mut self) {
+        self.step = self.step_for(self.frequency);
+    }
+
+    /// Phase increment per sample of each lane at the given frequency, negative
+    /// when the phase runs backwards.
+    fn step_for(&self, frequency: f32) -> f32x4 {
+        (self.ratios * f32x4::splat(frequency / self.sample_rate))
+            .max(f32x4::splat(-0.5))
+            .min(f32x4::splat(0.5))
+    }
+
+    /// Sets the phases of the time layout from the phase of the next sample, accumulating them
+    /// one sample at a time to round as the scalar oscillators do, and restarts the cycle of
+    /// the lanes synced within the next `samples` samples.
+    ///
+    /// Returns the phase of the sample after them with the sync residuals of each lane, carried
+    /// over from a sync in the previous sample and of a sync in the lane itself, if any.
+    fn advance_time_lanes(&mut self, samples: usize, increment: f32) -> (f32, Option<[f32x4; 2]>) {
+        let mut phases = [0.0; 4];
+        let mut phase = self.phase.to_array()[0];
+        let mut next_phase = phase;
+        if self.sync_residual == 0.0 && self.sync_fractions == [None; 4] {
+            for (lane, (lane_phase, wrap)) in phases.iter_mut().zip(&mut self.wrap_fractions).enumerate() {
+                *lane_phase = phase;
+                let advanced = phase + increment;
+                *wrap = wrap_fraction(advanced, increment);
+                phase = wrap_phase(advanced);
+                if lane + 1 == samples {
+                    next_phase = phase;
+                }
+            }
+            self.phase = f32x4::from(phases);
+
+            return (next_phase, None);
+        }
+
+        let sync_fractions = std::mem::take(&mut self.sync_fractions);
+        let mut carried = [0.0; 5];
+        let mut synced = [0.0; 4];
+        carried[0] = self.sync_residual;
+        for lane in 0..4 {
+            phases[lane] = phase;
+            match sync_fractions[lane].filter(|_| lane < samples) {
+                Some(fraction) => {
+                    let sync_phase = (phase + increment * (1.0 - fraction)).rem_euclid(1.0);
+                    phase = (fraction * increment).rem_euclid(1.0);
+                    self.wrap_fractions[lane] = Some(fraction);
+                    if self.quality == OscillatorQuality::BandLimited {
+                        let naive_sample = |phase| self.naive_sample(lane, phase);
+                        (synced[lane], carried[lane + 1]) = sync_residuals(self.waveform, naive_sample, sync_phase, fraction);
+                    }
+                },
+                None => {
+                    let advanced = phase + increment;
+                    self.wrap_fractions[lane] = wrap_fraction(advanced, increment);
+                    phase = wrap_phase(advanced);
+                },
+            }
+            if lane + 1 == samples {
+                next_phase = phase;
+            }
+        }
+        self.phase = f32x4::from(phases);
+        self.sync_residual = carried[samples];
+
+        (next_phase, Some([f32x4::from([carried[0], carried[1], carried[2], carried[3]]), f32x4::from(synced)]))
+    }
+
+    fn generate(&mut self, step: f32x4) -> f32x4 {
+        match self.waveform {
+            Waveform::Sine => self.generate_sine(),
+            Waveform::Square => self.generate_square(step),
+            Waveform::Triangle => self.generate_triangle(step),
+            Waveform::Sawtooth => self.generate_sawtooth(step),
+            Waveform::Noise => self.generate_noise(),
+        }
+    }
+
+    /// Naive waveform value of a lane at the given phase, noise is treated as silence.
+    fn naive_sample(&self, lane: usize, phase: f32) -> f32 {
+        match self.waveform {
+            Waveform::Sine => {
+                let phase = match self.shape {
+                    ShapeMode::PhaseDistortion => phase_distortion(phase, self.shape_amount.to_array()[lane]),
+                    _ => phase,
+                };
+                (2.0 * PI * phase).sin()
+            },
+            Waveform::Square => naive_square(phase, self.pulse_width.to_array()[lane]),
+            Waveform::Triangle => naive_triangle(phase),
+            Waveform::Sawtooth => naive_sawtooth(phase),
+            Waveform::Noise => 0.0,
         }
     }
 
     fn generate_sine(&self) -> f32x4 {
-        (f32x4::splat(2.0 * PI) * self.phase).sin()
+        let phase = match self.shape {
+            ShapeMode::PhaseDistortion => phase_distortion_x4(self.phase, self.shape_amount),
+            _ => self.phase,
+        };
+
+        (f32x4::splat(2.0 * PI) * phase).sin()
     }
 
-    fn generate_square(&self) -> f32x4 {
-        self.phase
-            .cmp_lt(0.5)
-            .blend(f32x4::splat(1.0), f32x4::splat(-1.0))
+    fn generate_square(&self, step: f32x4) -> f32x4 {
+        let naive = self.phase
+            .cmp_lt(self.pulse_width)
+            .blend(f32x4::splat(1.0), f32x4::splat(-1.0));
+
+        match self.quality {
+            OscillatorQuality::Naive => naive,
+            OscillatorQuality::BandLimited => {
+                // The falling edge sits at the pulse width.
+                let dt = step.abs();
+                let falling = self.phase + f32x4::ONE - self.pulse_width;
+                naive + poly_blep_x4(self.phase, dt) - poly_blep_x4(falling - falling.floor(), dt)
+            },
+        }
     }
 
-    fn generate_triangle(&self) -> f32x4 {
-        self.phase
+    fn generate_triangle(&self, step: f32x4) -> f32x4 {
+        let naive = self.phase
             .cmp_lt(0.5)
             .blend(
                 f32x4::splat(4.0) * self.phase - f32x4::splat(1.0),
                 f32x4::splat(3.0) - f32x4::splat(4.0) * self.phase
-            )
+            );
+
+        match self.quality {
+            OscillatorQuality::Naive => naive,
+            OscillatorQuality::BandLimited => {
+                // The slope flips by 8.0 per cycle at both corners.
+                let dt = step.abs();
+                naive + f32x4::splat(8.0) * dt
+                    * (poly_blamp_x4(self.phase, dt) - poly_blamp_x4(self.shifted_phase(0.5), dt))
+            },
+        }
     }
 
-    fn generate_sawtooth(&self) -> f32x4 {
-        f32x4::splat(2.0) * self.phase - f32x4::splat(1.0)
+    fn generate_sawtooth(&self, step: f32x4) -> f32x4 {
+        let naive = f32x4::splat(2.0) * self.phase - f32x4::splat(1.0);
+
+        match self.quality {
+            OscillatorQuality::Naive => naive,
+            OscillatorQuality::BandLimited => naive - poly_blep_x4(self.phase, step.abs()),
+        }
     }
 
-    /// TODO: simd rng
-    fn generate_noise(&self) -> f32x4 {
-        f32x4::from([
-            2.0 * random::<f32>() - 1.0,
-            2.0 * random::<f32>() - 1.0,
-            2.0 * random::<f32>() - 1.0,
-            2.0 * random::<f32>() - 1.0
-        ])
+    fn generate_noise(&mut self) -> f32x4 {
+        match self.layout {
+            LaneLayout::Time => self.noise.process_serial(),
+            LaneLayout::Parallel => self.noise.process(),
+        }
+    }
+
+    /// Phase shifted by the given part of a cycle, wrapped to `[0.0, 1.0)`.
+    fn shifted_phase(&self, shift: f32) -> f32x4 {
+        let shifted = self.phase + f32x4::splat(shift);
+        shifted - shifted.floor()
+    }
+}
+
+/// Wraps a phase advanced by at most half a cycle to `[0.0, 1.0)`, rounding as
+/// `rem_euclid(1.0)` does without its division.
+fn wrap_phase(phase: f32) -> f32 {
+    if phase >= 1.0 {
+        phase - 1.0
+    } else if phase < 0.0 {
+        phase + 1.0
+    } else {
+        phase
     }
 }
 
 impl Oscillator for SimdOscillator {
     type Output = f32x4;
 
-    /// Generate the next block of 4 samples for one voice.
+    /// Generates the next 4 samples of one voice, or the next sample of 4 lanes.
     fn tick(&mut self) -> f32x4 {
-        let sample = match self.waveform {
-            Waveform::Sine => self.generate_sine(),
-            Waveform::Square => self.generate_square(),
-            Waveform::Triangle => self.generate_triangle(),
-            Waveform::Sawtooth => self.generate_sawtooth(),
-            Waveform::Noise => self.generate_noise(),
-        };
-
-        // self.phase = self.phase + f32x4::splat(4.0 * self.step);
-        self.phase += f32x4::splat(4.0 * self.step);
-        // TODO: rem_euclid
-        self.phase = self.phase - self.phase.floor();
-
-        sample
+        self.tick_samples(4)
     }
 
     fn set_frequency(&mut self, frequency: f32) {
         self.frequency = frequency.clamp(0.0, self.sample_rate / 2.0);
-        self.step = self.frequency / self.sample_rate;
+        self.update_step();
+    }
+
+    /// In the time layout the offset applies to all four samples of the next tick.
+    fn modulate_frequency(&mut self, offset: f32) {
+        self.frequency_offset = offset;
     }
 
     fn set_waveform(&mut self, waveform: Waveform) {
@@ -93,14 +385,122 @@ impl Oscillator for SimdOscillator {
         }
     }
 
+    fn set_quality(&mut self, quality: OscillatorQuality) {
+        if self.quality != quality {
+            self.quality = quality;
+        }
+    }
+
+    fn set_pulse_width(&mut self, pulse_width: f32) {
+        self.pulse_width = f32x4::splat(pulse_width.clamp(0.01, 0.99));
+    }
+
+    fn set_shape(&mut self, mode: ShapeMode) {
+        if self.shape != mode {
+            self.shape = mode;
+        }
+    }
+
+    fn set_shape_amount(&mut self, amount: f32) {
+        self.shape_amount = f32x4::splat(amount.clamp(0.0, 1.0));
+    }
+
+    fn set_noise_color(&mut self, color: NoiseColor) {
+        self.noise.set_color(color);
+    }
+
+    fn set_seed(&mut self, seed: u32) {
+        self.noise.seed(seed);
+    }
+
     fn set_sample_rate(&mut self, sample_rate: f32) {
         if sample_rate > 0.0 {
             self.sample_rate = sample_rate;
-            self.step = self.frequency / self.sample_rate;
+            self.update_step();
         }
     }
 
     fn reset(&mut self) {
-        self.phase = f32x4::from([0.0, self.step, 2.0 * self.step, 3.0 * self.step]);
+        self.phase = f32x4::ZERO;
+        self.wrap_fractions = [None; 4];
+        self.sync_fractions = [None; 4];
+        self.sync_residual = 0.0;
+        self.update_step();
+    }
+}
+
+/// Wraps and syncs of the four samples of the time layout, the parallel layout neither
+/// reporting wraps nor following hard sync.
+impl HardSync for SimdOscillator {
+    type Wrap = [Option<f32>; 4];
+
+    fn wrapped(&self) -> [Option<f32>; 4] {
+        self.wrap_fractions
+    }
+
+    fn hard_sync(&mut self, wrap: [Option<f32>; 4]) {
+        self.sync_fractions = wrap.map(|fraction| fraction.map(|fraction| fraction.clamp(0.0, 1.0)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oscillator;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const FREQUENCY: f32 = 5000.0;
+
+    /// Alias ratio of the waveform at [`FREQUENCY`], whose aliases land on multiples of 1 kHz at 48 kHz.
+    fn alias_ratio(waveform: Waveform, quality: OscillatorQuality) -> f32 {
+        let mut oscillator = SimdOscillator::new(SAMPLE_RATE);
+        oscillator.set_waveform(waveform);
+        oscillator.set_quality(quality);
+        oscillator.set_frequency(FREQUENCY);
+        // 0.1 s holds whole periods of every multiple of 10 Hz.
+        let samples: Vec<f32> = (0..1200).flat_map(|_| oscillator.tick().to_array()).collect();
+
+        oscillator::alias_ratio(&samples, FREQUENCY, 1000.0, SAMPLE_RATE)
+    }
+
+    /// Alias ratio of a sawtooth at 1.37 times [`FREQUENCY`] hard-synced to a master at
+    /// [`FREQUENCY`], the synced waveform repeating at the master frequency.
+    fn sync_alias_ratio(quality: OscillatorQuality) -> f32 {
+        let mut master = SimdOscillator::new(SAMPLE_RATE);
+        master.set_frequency(FREQUENCY);
+        let mut slave = SimdOscillator::new(SAMPLE_RATE);
+        slave.set_waveform(Waveform::Sawtooth);
+        slave.set_quality(quality);
+        slave.set_frequency(1.37 * FREQUENCY);
+        let samples: Vec<f32> = (0..1200)
+            .flat_map(|_| {
+                master.tick();
+                slave.hard_sync(master.wrapped());
+                slave.tick().to_array()
+            })
+            .collect();
+
+        oscillator::alias_ratio(&samples, FREQUENCY, 1000.0, SAMPLE_RATE)
+    }
+
+    #[test]
+    fn band_limited_sawtooth_aliases_less_than_naive() {
+        let naive = alias_ratio(Waveform::Sawtooth, OscillatorQuality::Naive);
+        let band_limited = alias_ratio(Waveform::Sawtooth, OscillatorQuality::BandLimited);
+        assert!(band_limited < naive / 10.0, "naive {naive}, band-limited {band_limited}");
+    }
+
+    #[test]
+    fn band_limited_square_aliases_less_than_naive() {
+        let naive = alias_ratio(Waveform::Square, OscillatorQuality::Naive);
+        let band_limited = alias_ratio(Waveform::Square, OscillatorQuality::BandLimited);
+        assert!(band_limited < naive / 10.0, "naive {naive}, band-limited {band_limited}");
+    }
+
+    #[test]
+    fn band_limited_sync_aliases_less_than_naive() {
+        let naive = sync_alias_ratio(OscillatorQuality::Naive);
+        let band_limited = sync_alias_ratio(OscillatorQuality::BandLimited);
+        assert!(band_limited < naive / 10.0, "naive {naive}, band-limited {band_limited}");
     }
 }
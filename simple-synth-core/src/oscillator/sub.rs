@@ -0,0 +1,97 @@
+use std::f32::consts::PI;
+use super::{blep::poly_blep, OscillatorQuality, SubWaveform};
+
+/// Oscillator running one or two octaves below a master oscillator.
+///
+/// The sub has no phase of its own: it divides the master phase by counting the
+/// master cycles, so it stays phase-locked through pitch changes and FM.
+pub struct SubOscillator {
+    waveform: SubWaveform,
+    quality: OscillatorQuality,
+    /// Number of master cycles per sub cycle.
+    divisions: u32,
+    /// Master cycles elapsed in the current sub cycle.
+    cycle: u32,
+}
+
+impl SubOscillator {
+    pub fn new() -> Self {
+        Self {
+            waveform: SubWaveform::Square,
+            quality: OscillatorQuality::BandLimited,
+            divisions: 2,
+            cycle: 0,
+        }
+    }
+
+    pub fn set_waveform(&mut self, waveform: SubWaveform) {
+        if self.waveform != waveform {
+            self.waveform = waveform;
+        }
+    }
+
+    pub fn set_quality(&mut self, quality: OscillatorQuality) {
+        if self.quality != quality {
+            self.quality = quality;
+        }
+    }
+
+    /// Sets the octave below the master, -1 or -2.
+    pub fn set_octave(&mut self, octave: i32) {
+        self.divisions = 1 << octave.clamp(-2, -1).unsigned_abs();
+        self.cycle %= self.divisions;
+    }
+
+    /// Renders the sample matching the master phase and phase increment, read before ticking the master.
+    pub fn tick(&self, master_phase: f32, master_increment: f32) -> f32 {
+        let divisions = self.divisions as f32;
+        let phase = (self.cycle as f32 + master_phase) / divisions;
+
+        match self.waveform {
+            SubWaveform::Sine => (2.0 * PI * phase).sin(),
+            SubWaveform::Square => {
+                let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+                match self.quality {
+                    OscillatorQuality::Naive => naive,
+                    OscillatorQuality::BandLimited => {
+                        let dt = master_increment.abs() / divisions;
+                        naive + poly_blep(phase, dt) - poly_blep((phase + 0.5).fract(), dt)
+                    },
+                }
+            },
+        }
+    }
+
+    /// Counts the master cycles, pass whether the master wrapped after ticking it and the
+    /// phase increment passed to [`SubOscillator::tick`].
+    ///
+    /// A backward wrap under through-zero FM steps back a cycle.
+    pub fn follow(&mut self, master_wrapped: bool, master_increment: f32) {
+        if master_wrapped {
+            let step = if master_increment < 0.0 { self.divisions - 1 } else { 1 };
+            self.cycle = (self.cycle + step) % self.divisions;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.cycle = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backward_wraps_step_back_a_cycle() {
+        let mut sub = SubOscillator::new();
+        sub.set_octave(-2);
+        sub.follow(true, -0.1);
+        assert_eq!(sub.cycle, 3);
+        sub.follow(true, 0.1);
+        sub.follow(true, 0.1);
+        assert_eq!(sub.cycle, 1);
+        sub.follow(false, -0.1);
+        assert_eq!(sub.cycle, 1);
+    }
+}
@@ -0,0 +1,222 @@
+use std::{array::from_fn, f32::consts::{FRAC_PI_4, SQRT_2}};
+use wide::f32x4;
+use crate::rng::Rng;
+use super::{NoiseColor, Oscillator, OscillatorQuality, ShapeMode, SimdOscillator, Waveform};
+
+/// Maximum number of stacked unison voices.
+pub const MAX_UNISON_VOICES: usize = 16;
+
+const LANE_GROUPS: usize = MAX_UNISON_VOICES / 4;
+
+/// Stack of detuned oscillators rendered as a stereo pair.
+///
+/// Unison voices run as lanes of [`SimdOscillator`]s in the parallel layout,
+/// so four voices cost a single vectorised tick.
+pub struct UnisonOscillator {
+    groups: [SimdOscillator; LANE_GROUPS],
+    gains_left: [f32x4; LANE_GROUPS],
+    gains_right: [f32x4; LANE_GROUPS],
+    voices: usize,
+    detune: f32,
+    detune_curve: f32,
+    stereo_spread: f32,
+    random_phase: f32,
+    rng: Rng,
+}
+
+impl UnisonOscillator {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut oscillator = Self {
+            groups: from_fn(|_| SimdOscillator::parallel(sample_rate)),
+            gains_left: [f32x4::ZERO; LANE_GROUPS],
+            gains_right: [f32x4::ZERO; LANE_GROUPS],
+            voices: 1,
+            detune: 20.0,
+            detune_curve: 1.0,
+            stereo_spread: 0.5,
+            random_phase: 1.0,
+            rng: Rng::new(0),
+        };
+        oscillator.update_lanes();
+
+        oscillator
+    }
+
+    pub fn voices(&self) -> usize {
+        self.voices
+    }
+
+    /// Sets the number of stacked voices, from 1 to [`MAX_UNISON_VOICES`].
+    pub fn set_voices(&mut self, voices: usize) {
+        let voices = voices.clamp(1, MAX_UNISON_VOICES);
+        if self.voices != voices {
+            self.voices = voices;
+            self.update_lanes();
+        }
+    }
+
+    /// Sets the detune of the outermost voices in cents.
+    pub fn set_detune(&mut self, detune: f32) {
+        let detune = detune.clamp(0.0, 100.0);
+        if self.detune != detune {
+            self.detune = detune;
+            self.update_lanes();
+        }
+    }
+
+    /// Sets the exponent shaping the detune across the stack.
+    ///
+    /// 1.0 spreads the voices evenly, higher values cluster them around the centre.
+    pub fn set_detune_curve(&mut self, detune_curve: f32) {
+        let detune_curve = detune_curve.clamp(0.25, 4.0);
+        if self.detune_curve != detune_curve {
+            self.detune_curve = detune_curve;
+            self.update_lanes();
+        }
+    }
+
+    /// Sets how far the voices are panned apart, from 0.0 (mono) to 1.0 (full width).
+    pub fn set_stereo_spread(&mut self, stereo_spread: f32) {
+        let stereo_spread = stereo_spread.clamp(0.0, 1.0);
+        if self.stereo_spread != stereo_spread {
+            self.stereo_spread = stereo_spread;
+            self.update_lanes();
+        }
+    }
+
+    /// Sets the amount of random start phase applied on [`reset`](Oscillator::reset),
+    /// from 0.0 (all voices start at phase 0) to 1.0 (fully random).
+    pub fn set_random_phase(&mut self, random_phase: f32) {
+        self.random_phase = random_phase.clamp(0.0, 1.0);
+    }
+
+    /// Position of a voice in the stack, from -1.0 to 1.0.
+    fn lane_position(&self, voice: usize) -> f32 {
+        if self.voices > 1 {
+            2.0 * voice as f32 / (self.voices - 1) as f32 - 1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn update_lanes(&mut self) {
+        let normalization = 1.0 / (self.voices as f32).sqrt();
+
+        for group in 0..LANE_GROUPS {
+            let mut ratios = [1.0; 4];
+            let mut left = [0.0; 4];
+            let mut right = [0.0; 4];
+
+            for lane in 0..4 {
+                let voice = group * 4 + lane;
+                if voice >= self.voices {
+                    continue;
+                }
+
+                let position = self.lane_position(voice);
+                let cents = self.detune * position.signum() * position.abs().powf(self.detune_curve);
+                ratios[lane] = 2.0_f32.powf(cents / 1200.0);
+
+                // Equal-power pan, scaled so a centred voice has unity gain.
+                let angle = (self.stereo_spread * position + 1.0) * FRAC_PI_4;
+                left[lane] = angle.cos() * SQRT_2 * normalization;
+                right[lane] = angle.sin() * SQRT_2 * normalization;
+            }
+
+            self.groups[group].set_lane_ratios(ratios);
+            self.gains_left[group] = f32x4::from(left);
+            self.gains_right[group] = f32x4::from(right);
+        }
+    }
+
+    fn active_groups(&self) -> usize {
+        self.voices.div_ceil(4)
+    }
+}
+
+impl Oscillator for UnisonOscillator {
+    /// Left and right samples.
+    type Output = (f32, f32);
+
+    fn tick(&mut self) -> (f32, f32) {
+        let mut left = f32x4::ZERO;
+        let mut right = f32x4::ZERO;
+
+        for group in 0..self.active_groups() {
+            let samples = self.groups[group].tick();
+            left += samples * self.gains_left[group];
+            right += samples * self.gains_right[group];
+        }
+
+        (left.reduce_add(), right.reduce_add())
+    }
+
+    fn set_frequency(&mut self, frequency: f32) {
+        for group in &mut self.groups {
+            group.set_frequency(frequency);
+        }
+    }
+
+    fn modulate_frequency(&mut self, offset: f32) {
+        let active_groups = self.active_groups();
+        for group in &mut self.groups[..active_groups] {
+            group.modulate_frequency(offset);
+        }
+    }
+
+    fn set_waveform(&mut self, waveform: Waveform) {
+        for group in &mut self.groups {
+            group.set_waveform(waveform);
+        }
+    }
+
+    fn set_quality(&mut self, quality: OscillatorQuality) {
+        for group in &mut self.groups {
+            group.set_quality(quality);
+        }
+    }
+
+    fn set_pulse_width(&mut self, pulse_width: f32) {
+        for group in &mut self.groups {
+            group.set_pulse_width(pulse_width);
+        }
+    }
+
+    fn set_shape(&mut self, mode: ShapeMode) {
+        for group in &mut self.groups {
+            group.set_shape(mode);
+        }
+    }
+
+    fn set_shape_amount(&mut self, amount: f32) {
+        for group in &mut self.groups {
+            group.set_shape_amount(amount);
+        }
+    }
+
+    fn set_noise_color(&mut self, color: NoiseColor) {
+        for group in &mut self.groups {
+            group.set_noise_color(color);
+        }
+    }
+
+    fn set_seed(&mut self, seed: u32) {
+        self.rng.seed(seed);
+        for (index, group) in self.groups.iter_mut().enumerate() {
+            group.set_seed(seed.wrapping_add(index as u32 + 1));
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        for group in &mut self.groups {
+            group.set_sample_rate(sample_rate);
+        }
+    }
+
+    fn reset(&mut self) {
+        for group in &mut self.groups {
+            group.reset();
+            group.set_lane_phases(from_fn(|_| self.random_phase * self.rng.next_f32()));
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use std::{
+    f64::consts::TAU,
+    fmt,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+use crate::wav::{WavError, WavFile};
+use super::{wrap_fraction, HardSync, NoiseColor, Oscillator, OscillatorQuality, ShapeMode, Waveform};
+
+/// Frame size assumed for wavetable files without a `clm ` chunk.
+const DEFAULT_FRAME_SIZE: usize = 2048;
+/// Longest frame of a wavetable, in samples.
+pub const MAX_WAVETABLE_FRAME_SIZE: usize = 4096;
+/// Most frames of a wavetable.
+pub const MAX_WAVETABLE_FRAMES: usize = 256;
+
+/// Errors produced while building a wavetable from frames or from a WAV file.
+#[derive(Debug)]
+pub enum WavetableError {
+    /// The WAV file could not be read.
+    Wav(WavError),
+    /// There are no frames or samples.
+    Empty,
+    /// The frame size is invalid or the sample count is not a multiple of it.
+    InvalidFrameSize(usize),
+    /// The wavetable holds more frames than supported.
+    TooManyFrames(usize),
+}
+
+impl fmt::Display for WavetableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavetableError::Wav(error) => write!(f, "failed to read wavetable: {error}"),
+            WavetableError::Empty => write!(f, "wavetable contains no samples"),
+            WavetableError::InvalidFrameSize(size) => write!(f, "invalid wavetable frame size {size}"),
+            WavetableError::TooManyFrames(count) => write!(f, "wavetable has too many frames ({count})"),
+        }
+    }
+}
+
+impl std::error::Error for WavetableError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WavetableError::Wav(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<WavError> for WavetableError {
+    fn from(error: WavError) -> Self {
+        WavetableError::Wav(error)
+    }
+}
+
+/// A stack of single-cycle frames with per-octave band-limited mip-maps.
+///
+/// Level 0 keeps every harmonic the frame can hold, each following level halves
+/// the highest harmonic, down to a pure fundamental.
+pub struct Wavetable {
+    frame_len: usize,
+    frame_count: usize,
+    level_count: usize,
+    /// Highest harmonic kept at each mip level.
+    max_harmonics: Vec<usize>,
+    /// Tables indexed by `frame * level_count + level`, each with one guard sample.
+    tables: Vec<Vec<f32>>,
+}
+
+impl Wavetable {
+    /// Builds a wavetable from up to [`MAX_WAVETABLE_FRAMES`] single-cycle frames of equal,
+    /// power-of-two length from 4 to [`MAX_WAVETABLE_FRAME_SIZE`].
+    ///
+    /// Returns [`WavetableError::Empty`] without frames, [`WavetableError::TooManyFrames`] past
+    /// the limit and [`WavetableError::InvalidFrameSize`] with the length of the first frame that is not a
+    /// valid or equal length.
+    pub fn new(frames: &[Vec<f32>]) -> Result<Self, WavetableError> {
+        let Some(first) = frames.first() else {
+            return Err(WavetableError::Empty);
+        };
+        if frames.len() > MAX_WAVETABLE_FRAMES {
+            return Err(WavetableError::TooManyFrames(frames.len()));
+        }
+        let frame_len = first.len();
+        if !(4..=MAX_WAVETABLE_FRAME_SIZE).contains(&frame_len) || !frame_len.is_power_of_two() {
+            return Err(WavetableError::InvalidFrameSize(frame_len));
+        }
+        if let Some(frame) = frames.iter().find(|frame| frame.len() != frame_len) {
+            return Err(WavetableError::InvalidFrameSize(frame.len()));
+        }
+
+        let half = frame_len / 2;
+        let level_count = half.trailing_zeros() as usize + 1;
+        let max_harmonics: Vec<usize> = (0..level_count)
+            .map(|level| if level == 0 { half - 1 } else { half >> level })
+            .collect();
+
+        let mut tables = Vec::with_capacity(frames.len() * level_count);
+        let mut spectrum_re = vec![0.0; frame_len];
+        let mut spectrum_im = vec![0.0; frame_len];
+        let mut re = vec![0.0; frame_len];
+        let mut im = vec![0.0; frame_len];
+
+        for frame in frames {
+            spectrum_re.copy_from_slice(frame);
+            spectrum_im.fill(0.0);
+            fft(&mut spectrum_re, &mut spectrum_im, false);
+
+            for &max_harmonic in &max_harmonics {
+                re.fill(0.0);
+                im.fill(0.0);
+                for bin in 1..=max_harmonic {
+                    re[bin] = spectrum_re[bin];
+                    im[bin] = spectrum_im[bin];
+                    re[frame_len - bin] = spectrum_re[frame_len - bin];
+                    im[frame_len - bin] = spectrum_im[frame_len - bin];
+                }
+                fft(&mut re, &mut im, true);
+
+                let mut table = Vec::with_capacity(frame_len + 1);
+                table.extend_from_slice(&re);
+                table.push(re[0]);
+                tables.push(table);
+            }
+        }
+
+        Ok(Self {
+            frame_len,
+            frame_count: frames.len(),
+            level_count,
+            max_harmonics,
+            tables,
+        })
+    }
+
+    /// Loads a wavetable from a WAV file on disk, see [`Wavetable::from_wav`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WavetableError> {
+        Self::from_wav(&WavFile::open(path)?)
+    }
+
+    /// Builds a wavetable from a decoded WAV file.
+    ///
+    /// The frame size comes from the Serum `clm ` chunk when present, otherwise
+    /// files holding a multiple of 2048 samples are split into 2048-sample frames
+    /// and anything else is treated as a single cycle. Frames whose length is not
+    /// a power of two are resampled, and the whole table is normalized to a peak of 1.0.
+    ///
+    /// Frames longer than [`MAX_WAVETABLE_FRAME_SIZE`] and tables of more than
+    /// [`MAX_WAVETABLE_FRAMES`] frames, such as long recordings, are rejected before
+    /// anything is resampled.
+    pub fn from_wav(wav: &WavFile) -> Result<Self, WavetableError> {
+        let samples = wav.to_mono();
+        let frame_size = match wav.wavetable_frame_size {
+            Some(size) => size,
+            None if samples.len().is_multiple_of(DEFAULT_FRAME_SIZE) => DEFAULT_FRAME_SIZE,
+            None => samples.len(),
+        };
+        if !(4..=MAX_WAVETABLE_FRAME_SIZE).contains(&frame_size) || !samples.len().is_multiple_of(frame_size) {
+            return Err(WavetableError::InvalidFrameSize(frame_size));
+        }
+        if samples.len() / frame_size > MAX_WAVETABLE_FRAMES {
+            return Err(WavetableError::TooManyFrames(samples.len() / frame_size));
+        }
+
+        let frame_len = if frame_size.is_power_of_two() {
+            frame_size
+        } else {
+            frame_size.next_power_of_two().clamp(DEFAULT_FRAME_SIZE, MAX_WAVETABLE_FRAME_SIZE)
+        };
+
+        let peak = samples.iter().fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        let gain = if peak > 0.0 { 1.0 / peak } else { 1.0 };
+
+        let frames: Vec<Vec<f32>> = samples
+            .chunks_exact(frame_size)
+            .map(|frame| {
+                (0..frame_len)
+                    .map(|i| {
+                        // Linear resampling, wrapping around the single cycle.
+                        let position = i as f32 * frame_size as f32 / frame_len as f32;
+                        let index = position as usize;
+                        let fraction = position - index as f32;
+                        let current = frame[index];
+                        let next = frame[(index + 1) % frame_size];
+                        (current + (next - current) * fraction) * gain
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self::new(&frames)
+    }
+
+    /// Returns the shared built-in table morphing sine, triangle, sawtooth and square.
+    pub fn basic_shapes() -> Arc<Wavetable> {
+        static BASIC_SHAPES: OnceLock<Arc<Wavetable>> = OnceLock::new();
+
+        BASIC_SHAPES
+            .get_or_init(|| {
+                const LEN: usize = 2048;
+                let shape = |f: fn(f32) -> f32| -> Vec<f32> {
+                    (0..LEN).map(|i| f(i as f32 / LEN as f32)).collect()
+                };
+
+                let table = Wavetable::new(&[
+                    shape(|p| (std::f32::consts::TAU * p).sin()),
+                    shape(|p| if p < 0.25 { 4.0 * p } else if p < 0.75 { 2.0 - 4.0 * p } else { 4.0 * p - 4.0 }),
+                    shape(|p| if p < 0.5 { 2.0 * p } else { 2.0 * p - 2.0 }),
+                    shape(|p| if p < 0.5 { 1.0 } else { -1.0 }),
+                ]);
+                Arc::new(table.expect("Built-in frames have a valid length."))
+            })
+            .clone()
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Returns the first mip level whose harmonics all stay below Nyquist
+    /// for the given phase increment per sample.
+    fn level_for(&self, phase_increment: f32) -> usize {
+        self.max_harmonics
+            .iter()
+            .position(|&harmonic| harmonic as f32 * phase_increment < 0.5)
+            .unwrap_or(self.level_count - 1)
+    }
+
+    /// Reads a frame at the given mip level with linear interpolation.
+    fn read(&self, frame: usize, level: usize, phase: f32) -> f32 {
+        let table = &self.tables[frame * self.level_count + level];
+        let position = phase * self.frame_len as f32;
+        let index = (position as usize).min(self.frame_len - 1);
+        let fraction = position - index as f32;
+
+        table[index] + (table[index + 1] - table[index]) * fraction
+    }
+}
+
+/// In-place iterative radix-2 FFT; the inverse transform is scaled by `1 / n`.
+fn fft(re: &mut [f32], im: &mut [f32], inverse: bool) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let angle = sign * TAU / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..half {
+                let (w_im, w_re) = (angle * k as f64).sin_cos();
+                let (w_re, w_im) = (w_re as f32, w_im as f32);
+                let (a, b) = (start + k, start + k + half);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f32;
+        re.iter_mut().for_each(|x| *x *= scale);
+        im.iter_mut().for_each(|x| *x *= scale);
+    }
+}
+
+pub struct WavetableOscillator {
+    table: Arc<Wavetable>,
+    phase: f32,
+    sample_rate: f32,
+    frequency: f32,
+    /// Frequency offset in hertz applied to the next tick.
+    frequency_offset: f32,
+    position: f32,
+    level: usize,
+    wrap_fraction: Option<f32>,
+    sync_fraction: Option<f32>,
+}
+
+impl WavetableOscillator {
+    pub fn new(sample_rate: f32) -> Self {
+        assert!(sample_rate > 0.0, "Sample rate must be greater than 0.");
+
+        let mut oscillator = Self {
+            table: Wavetable::basic_shapes(),
+            phase: 0.0,
+            sample_rate,
+            frequency: 440.0,
+            frequency_offset: 0.0,
+            position: 0.0,
+            level: 0,
+            wrap_fraction: None,
+            sync_fraction: None,
+        };
+        oscillator.update_level();
+
+        oscillator
+    }
+
+    /// Replaces the wavetable; the table is shared, not copied.
+    pub fn set_wavetable(&mut self, table: Arc<Wavetable>) {
+        self.table = table;
+        self.update_level();
+    }
+
+    /// Sets the morph position across the frames, from 0.0 (first) to 1.0 (last).
+    pub fn set_position(&mut self, position: f32) {
+        self.position = position.clamp(0.0, 1.0);
+    }
+
+    /// Phase of the next sample, from 0.0 to 1.0.
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Sets the phase of the next sample, wrapped to `[0.0, 1.0)`.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = phase.rem_euclid(1.0);
+    }
+
+    /// Phase increment per sample, negative when the phase runs backwards.
+    pub fn phase_increment(&self) -> f32 {
+        ((self.frequency + self.frequency_offset) / self.sample_rate).clamp(-0.5, 0.5)
+    }
+
+    fn update_level(&mut self) {
+        self.level = self.table.level_for(self.phase_increment().abs());
+    }
+}
+
+impl Oscillator for WavetableOscillator {
+    type Output = f32;
+
+    fn tick(&mut self) -> f32 {
+        if self.frequency_offset != 0.0 {
+            self.update_level();
+        }
+
+        let frame_position = self.position * (self.table.frame_count() - 1) as f32;
+        let frame = frame_position as usize;
+        let next_frame = (frame + 1).min(self.table.frame_count() - 1);
+        let fraction = frame_position - frame as f32;
+
+        let current = self.table.read(frame, self.level, self.phase);
+        let sample = if fraction > 0.0 {
+            current + (self.table.read(next_frame, self.level, self.phase) - current) * fraction
+        } else {
+            current
+        };
+
+        let increment = self.phase_increment();
+        if let Some(fraction) = self.sync_fraction.take() {
+            self.phase = (fraction * increment).rem_euclid(1.0);
+            self.wrap_fraction = Some(fraction);
+        } else {
+            let phase = self.phase + increment;
+            self.wrap_fraction = wrap_fraction(phase, increment);
+            self.phase = phase.rem_euclid(1.0);
+        }
+
+        if self.frequency_offset != 0.0 {
+            self.frequency_offset = 0.0;
+            self.update_level();
+        }
+
+        sample
+    }
+
+    fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.clamp(0.0, self.sample_rate / 2.0);
+        self.update_level();
+    }
+
+    fn modulate_frequency(&mut self, offset: f32) {
+        self.frequency_offset = offset;
+    }
+
+    /// The shape comes from the wavetable, so the waveform is ignored.
+    fn set_waveform(&mut self, _waveform: Waveform) {}
+
+    /// Wavetables are always band-limited through their mip-maps.
+    fn set_quality(&mut self, _quality: OscillatorQuality) {}
+
+    /// The shape comes from the wavetable, so the pulse width is ignored.
+    fn set_pulse_width(&mut self, _pulse_width: f32) {}
+
+    /// The shape comes from the wavetable, so the shaping stage is ignored.
+    fn set_shape(&mut self, _mode: ShapeMode) {}
+
+    fn set_shape_amount(&mut self, _amount: f32) {}
+
+    fn set_noise_color(&mut self, _color: NoiseColor) {}
+
+    fn set_seed(&mut self, _seed: u32) {}
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate > 0.0 {
+            self.sample_rate = sample_rate;
+            self.update_level();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        self.wrap_fraction = None;
+        self.sync_fraction = None;
+    }
+}
+
+/// Hard sync restarts the table without band-limiting the jump.
+impl HardSync for WavetableOscillator {
+    type Wrap = Option<f32>;
+
+    fn wrapped(&self) -> Option<f32> {
+        self.wrap_fraction
+    }
+
+    fn hard_sync(&mut self, wrap: Option<f32>) {
+        self.sync_fraction = wrap.map(|fraction| fraction.clamp(0.0, 1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_rejects_invalid_frames() {
+        assert!(matches!(Wavetable::new(&[]), Err(WavetableError::Empty)));
+        assert!(matches!(Wavetable::new(&[vec![0.0; 100]]), Err(WavetableError::InvalidFrameSize(100))));
+        assert!(matches!(Wavetable::new(&[vec![0.0; 2]]), Err(WavetableError::InvalidFrameSize(2))));
+        assert!(matches!(
+            Wavetable::new(&[vec![0.0; 256], vec![0.0; 128]]),
+            Err(WavetableError::InvalidFrameSize(128)),
+        ));
+    }
+
+    fn mono(samples: Vec<f32>, wavetable_frame_size: Option<usize>) -> WavFile {
+        WavFile {
+            sample_rate: 48000,
+            channels: 1,
+            samples,
+            wavetable_frame_size,
+            root_key: None,
+            loop_points: None,
+        }
+    }
+
+    #[test]
+    fn from_wav_splits_clm_frames() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/clm.wav");
+        let table = Wavetable::from_wav(&WavFile::open(path).unwrap()).unwrap();
+        assert_eq!(table.frame_len(), 256);
+        assert_eq!(table.frame_count(), 2);
+    }
+
+    #[test]
+    fn from_wav_rejects_oversized_tables() {
+        assert!(matches!(
+            Wavetable::from_wav(&mono(vec![0.5; 10000], None)),
+            Err(WavetableError::InvalidFrameSize(10000)),
+        ));
+        assert!(matches!(
+            Wavetable::from_wav(&mono(vec![0.5; 1000], Some(300))),
+            Err(WavetableError::InvalidFrameSize(300)),
+        ));
+        assert!(matches!(
+            Wavetable::from_wav(&mono(vec![0.5; 257 * DEFAULT_FRAME_SIZE], None)),
+            Err(WavetableError::TooManyFrames(257)),
+        ));
+        assert!(matches!(Wavetable::new(&[vec![0.0; 8192]]), Err(WavetableError::InvalidFrameSize(8192))));
+    }
+
+    #[test]
+    fn new_builds_a_mip_level_per_octave() {
+        let table = Wavetable::new(&[vec![0.0; 256], vec![1.0; 256]]).unwrap();
+        assert_eq!(table.frame_len(), 256);
+        assert_eq!(table.frame_count(), 2);
+        assert_eq!(table.level_count, 8);
+    }
+}
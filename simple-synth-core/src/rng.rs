@@ -0,0 +1,91 @@
+use wide::{f32x4, i32x4};
+
+/// Scale from a 24-bit integer to `[0.0, 1.0)`.
+const UNIT_SCALE: f32 = 1.0 / (1 << 24) as f32;
+
+/// Small xorshift32 generator, cheap and allocation-free on the audio thread.
+///
+/// The same seed always produces the same sequence, so renders are reproducible.
+#[derive(Clone, Copy, Debug)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        Self { state: scramble(seed) }
+    }
+
+    /// Restarts the sequence from the given seed.
+    pub fn seed(&mut self, seed: u32) {
+        self.state = scramble(seed);
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+
+        x
+    }
+
+    /// Uniform value in `[0.0, 1.0)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * UNIT_SCALE
+    }
+
+    /// Uniform value in `[-1.0, 1.0)`.
+    pub fn next_bipolar(&mut self) -> f32 {
+        2.0 * self.next_f32() - 1.0
+    }
+}
+
+/// Four independent xorshift32 generators, one per lane.
+#[derive(Clone, Copy, Debug)]
+pub struct RngX4 {
+    state: i32x4,
+}
+
+impl RngX4 {
+    pub fn new(seed: u32) -> Self {
+        let mut rng = Self { state: i32x4::ZERO };
+        rng.seed(seed);
+
+        rng
+    }
+
+    /// Restarts the sequence of every lane from the given seed.
+    pub fn seed(&mut self, seed: u32) {
+        let mut seeds = Rng::new(seed);
+        self.state = i32x4::from([0; 4].map(|_: i32| scramble(seeds.next_u32()) as i32));
+    }
+
+    /// Uniform values in `[0.0, 1.0)`.
+    pub fn next_f32(&mut self) -> f32x4 {
+        // Signed shifts are arithmetic, so mask off the sign fill to get logical shifts.
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= (x >> 17) & i32x4::splat(0x7fff);
+        x ^= x << 5;
+        self.state = x;
+
+        f32x4::from_i32x4((x >> 8) & i32x4::splat(0xff_ffff)) * f32x4::splat(UNIT_SCALE)
+    }
+
+    /// Uniform values in `[-1.0, 1.0)`.
+    pub fn next_bipolar(&mut self) -> f32x4 {
+        f32x4::splat(2.0) * self.next_f32() - f32x4::ONE
+    }
+}
+
+/// Spreads the bits of a seed so nearby seeds give unrelated sequences, never returning 0.
+fn scramble(seed: u32) -> u32 {
+    let mut x = seed.wrapping_add(0x9e37_79b9);
+    x = (x ^ (x >> 16)).wrapping_mul(0x85eb_ca6b);
+    x = (x ^ (x >> 13)).wrapping_mul(0xc2b2_ae35);
+    x ^= x >> 16;
+
+    if x == 0 { 0x6d2b_79f5 } else { x }
+}
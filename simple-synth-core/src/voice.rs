@@ -1,68 +1,449 @@
+use std::sync::Arc;
+use wide::f32x4;
 use crate::{
-    envelope::{Envelope, LinearEnvelope},
+    envelope::{Breakpoint, BreakpointEnvelope, Envelope, EnvelopeType, ExponentialEnvelope, RetriggerMode},
     EnvelopeStage,
-    filter::Filter,
-    oscillator::{BasicOscillator, Oscillator},
+    filter::{Filter, FilterMode},
+    lfo::{Drift, Lfo},
+    oscillator::{
+        FmMode,
+        HardSync,
+        MultiOscillator,
+        Oscillator,
+        OscillatorType,
+        PhaseMode,
+        Sample,
+        ShapeMode,
+        SimdOscillator,
+        SubOscillator,
+        UnisonOscillator,
+        Waveform,
+        Wavetable,
+    },
+    rng::Rng,
     SynthParam
 };
 
+/// Samples between two updates of the oscillator frequencies under drift, the sync envelope or
+/// the breakpoint envelope.
+const PITCH_INTERVAL: u32 = 32;
+
+/// Maps a note-on velocity to how strongly it drives the velocity destinations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VelocityCurve {
+    Linear,
+    /// Rises quickly, soft notes already reaching most of the range.
+    Soft,
+    /// Rises slowly, only hard notes reaching the top of the range.
+    Hard,
+}
+
+impl VelocityCurve {
+    fn apply(self, velocity: f32) -> f32 {
+        let velocity = velocity.clamp(0.0, 1.0);
+        match self {
+            VelocityCurve::Linear => velocity,
+            VelocityCurve::Soft => velocity.sqrt(),
+            VelocityCurve::Hard => velocity * velocity,
+        }
+    }
+}
+
+/// State of a voice rendered as one lane of the voice bank, see [`Voice::lane`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct VoiceLane {
+    /// Phases of oscillators 1 and 2.
+    pub phases: [f32; 2],
+    /// Frequencies of oscillators 1 and 2 in hertz.
+    pub frequencies: [f32; 2],
+    pub oscillator_mix: f32,
+    pub ring_mod: f32,
+    pub last_oscillator2: f32,
+    /// Amplitude and filter envelope states.
+    pub envelopes: [(EnvelopeStage, f32); 2],
+    /// Signed attack, decay and release steps of the amplitude and filter envelopes.
+    pub envelope_steps: [[f32; 3]; 2],
+    pub sustain_levels: [f32; 2],
+    pub filter_envelope_amount: f32,
+    /// Amplitude scaling by the note velocity.
+    pub gain: f32,
+    pub filter_mode: FilterMode,
+    pub cutoff: f32,
+    /// Left and right filter states.
+    pub filters: [[f32; 4]; 2],
+}
+
 pub struct Voice {
-    oscillator1: BasicOscillator,
-    // oscillator2: Oscillator,
-    envelope1: LinearEnvelope,
+    oscillator1: MultiOscillator,
+    oscillator2: MultiOscillator,
+    unison: UnisonOscillator,
+    /// Oscillators 1 and 2 rendering four samples per tick for [`Voice::process_block`].
+    block_oscillators: [SimdOscillator; 2],
+    oscillator2_octave: i32,
+    oscillator2_semitone: i32,
+    oscillator2_fine: f32,
+    oscillator_mix: f32,
+    /// Square or sine wave phase-locked below oscillator 1.
+    sub: SubOscillator,
+    sub_level: f32,
+    /// Hard-syncs oscillator 2 (slave) to oscillator 1 (master).
+    sync: bool,
+    /// Slave pitch offset in semitones while synced.
+    sync_pitch: f32,
+    /// Filter envelope modulation of the slave pitch in semitones.
+    sync_envelope_amount: f32,
+    /// Frequency modulation of oscillator 1 by oscillator 2.
+    fm_mode: FmMode,
+    /// Peak frequency deviation as a multiple of the oscillator 2 frequency.
+    fm_index: f32,
+    /// Crossfade from the oscillator mix to the ring-modulated product.
+    ring_mod: f32,
+    /// Last oscillator 2 sample, modulating oscillator 1 on the next sample.
+    last_oscillator2: f32,
+    pulse_width: f32,
+    /// Pulse width modulation depth around the pulse width.
+    pwm_depth: f32,
+    pwm_lfo: Lfo,
+    /// Amount of the phase distortion, wavefolder or waveshaper.
+    shape_amount: f32,
+    /// Filter envelope modulation of the shape amount.
+    shape_envelope_amount: f32,
+    /// Slow random pitch wander of oscillators 1 and 2.
+    drift: [Drift; 2],
+    /// Depth of the pitch wander in cents.
+    drift_amount: f32,
+    /// Samples since the modulated frequencies were last updated.
+    since_pitch: u32,
+    /// Maximum random detune in cents drawn for each note.
+    random_detune: f32,
+    /// Detune of oscillators 1 and 2 in cents drawn at the last note-on.
+    detune: [f32; 2],
+    /// Phase behaviour of oscillators 1 and 2 at note-on.
+    phase_modes: [PhaseMode; 2],
+    /// How the envelopes restart when the sounding note is played again.
+    retrigger_mode: RetriggerMode,
+    frequency: f32,
+    /// Frequencies of oscillators 1 and 2 after detune and drift.
+    oscillator1_frequency: f32,
+    oscillator2_frequency: f32,
+    envelope1: ExponentialEnvelope,
     // envelope2: Envelope,
-    filter: Filter,
-    filter_envelope: LinearEnvelope,
+    /// Left and right channel filters.
+    filters: [Filter; 2],
+    filter_envelope: ExponentialEnvelope,
+    /// Looping multi-segment envelope, replacing the filter envelope when selected.
+    breakpoint_envelope: BreakpointEnvelope,
+    /// Breakpoint envelope modulation of both oscillator pitches in semitones.
+    breakpoint_pitch: f32,
+    /// Breakpoint envelope modulation of the cutoff, on top of the filter envelope.
+    breakpoint_cutoff: f32,
+    /// Breakpoint envelope modulation of the oscillator mix.
+    breakpoint_mix: f32,
+    /// Breakpoint envelope modulation of the pulse width.
+    breakpoint_pulse_width: f32,
+    /// Breakpoint envelope modulation of the shape amount.
+    breakpoint_shape: f32,
+    filter_envelope_type: EnvelopeType,
     filter_envelope_amount: f32,
+    /// Attack times of the amplitude and filter envelopes before velocity scaling.
+    attack_times: [f32; 2],
     note_number: Option<u8>,
-    // velocity: Option<u8>,
+    /// Velocity of the sounding note after the velocity curve, from 0.0 to 1.0.
+    velocity: f32,
+    /// Velocity of a note played again, taken on with the oscillators starting over once the
+    /// restart fade out is over.
+    pending_velocity: Option<f32>,
+    velocity_curve: VelocityCurve,
+    /// How much velocity lowers the amplitude of soft notes, from 0.0 to 1.0.
+    velocity_amplitude: f32,
+    /// How much velocity lowers the filter envelope depth of soft notes, from 0.0 to 1.0.
+    velocity_filter_envelope: f32,
+    /// How much velocity shortens the attack of hard notes, from 0.0 to 1.0.
+    velocity_attack: f32,
     active: bool,
+    rng: Rng,
 }
 
 impl Voice {
     pub fn new(sample_rate: f32) -> Self {
         Self {
-            oscillator1: BasicOscillator::new(sample_rate),
-            // oscillator2: Oscillator::new(sample_rate),
-            envelope1: LinearEnvelope::new(sample_rate),
+            oscillator1: MultiOscillator::new(sample_rate),
+            oscillator2: MultiOscillator::new(sample_rate),
+            unison: UnisonOscillator::new(sample_rate),
+            block_oscillators: [SimdOscillator::new(sample_rate), SimdOscillator::new(sample_rate)],
+            oscillator2_octave: 0,
+            oscillator2_semitone: 0,
+            oscillator2_fine: 0.0,
+            oscillator_mix: 0.0,
+            sub: SubOscillator::new(),
+            sub_level: 0.0,
+            sync: false,
+            sync_pitch: 0.0,
+            sync_envelope_amount: 0.0,
+            fm_mode: FmMode::Off,
+            fm_index: 0.0,
+            ring_mod: 0.0,
+            last_oscillator2: 0.0,
+            pulse_width: 0.5,
+            pwm_depth: 0.0,
+            pwm_lfo: Lfo::new(sample_rate),
+            shape_amount: 0.0,
+            shape_envelope_amount: 0.0,
+            drift: [Drift::new(sample_rate), Drift::new(sample_rate)],
+            drift_amount: 0.0,
+            since_pitch: 0,
+            random_detune: 0.0,
+            detune: [0.0; 2],
+            phase_modes: [PhaseMode::FreeRunning; 2],
+            retrigger_mode: RetriggerMode::Reset,
+            frequency: 440.0,
+            oscillator1_frequency: 440.0,
+            oscillator2_frequency: 440.0,
+            envelope1: ExponentialEnvelope::new(sample_rate),
             // envelope2: Envelope::new(sample_rate),
-            filter: Filter::new(),
-            filter_envelope: LinearEnvelope::new(sample_rate),
+            filters: [Filter::new(sample_rate), Filter::new(sample_rate)],
+            filter_envelope: ExponentialEnvelope::new(sample_rate),
+            breakpoint_envelope: BreakpointEnvelope::new(sample_rate),
+            breakpoint_pitch: 0.0,
+            breakpoint_cutoff: 0.0,
+            breakpoint_mix: 0.0,
+            breakpoint_pulse_width: 0.0,
+            breakpoint_shape: 0.0,
+            filter_envelope_type: EnvelopeType::Adsr,
             filter_envelope_amount: 0.0,
+            attack_times: [0.1; 2],
             note_number: None,
-            // velocity: None,
+            velocity: 1.0,
+            pending_velocity: None,
+            velocity_curve: VelocityCurve::Linear,
+            velocity_amplitude: 0.0,
+            velocity_filter_envelope: 0.0,
+            velocity_attack: 0.0,
             active: false,
+            rng: Rng::new(0),
         }
     }
 
-    pub fn process(&mut self) -> f32 {
+    /// Renders the next left and right samples of the voice.
+    pub fn process(&mut self) -> (f32, f32) {
         if !self.active {
-            return 0.0;
+            return (0.0, 0.0);
         }
 
         if self.envelope1.is_idle() {
             self.active = false;
             self.note_number = None;
-            return 0.0;
+            return (0.0, 0.0);
+        }
+
+        self.follow_restart();
+        let breakpoint = self.process_breakpoint_envelope();
+        let filter_envelope = self.process_filter_envelope(breakpoint);
+        let cutoff_mod = filter_envelope * self.filter_envelope_depth() + breakpoint * self.breakpoint_cutoff; //+ lfo * filter_lfo_amount
+        self.set_cutoff_mod(cutoff_mod);
+
+        if self.pulse_width_modulated() {
+            let mut pulse_width = self.pulse_width + breakpoint * self.breakpoint_pulse_width;
+            if self.pwm_depth > 0.0 {
+                pulse_width += self.pwm_depth * self.pwm_lfo.process();
+            }
+            self.set_pulse_width(pulse_width);
+        }
+
+        if self.shape_modulated() {
+            self.set_shape_amount(self.shape_amount + filter_envelope * self.shape_envelope_amount + breakpoint * self.breakpoint_shape);
+        }
+
+        if self.fm_mode != FmMode::Off {
+            let mut deviation = self.fm_index * self.oscillator2_frequency * self.last_oscillator2;
+            if self.fm_mode == FmMode::Linear {
+                deviation = deviation.max(-self.oscillator1_frequency);
+            }
+            self.oscillator1.modulate_frequency(deviation);
+            if self.unison_enabled() {
+                self.unison.modulate_frequency(deviation);
+            }
         }
 
-        self.filter.set_cutoff_mod(self.filter_envelope.process() * self.filter_envelope_amount); //+ lfo * filter_lfo_amount
-        self.filter.process(self.oscillator1.tick() * self.envelope1.process())
-        // self.oscillator1.tick() * self.envelope1.process() * (self.velocity / 127.0)
+        let increment = self.oscillator1.phase_increment();
+        let sub = self.sub.tick(self.oscillator1.phase(), increment) * self.sub_level;
+        let oscillator1 = self.oscillator1.tick();
+        self.sub.follow(self.oscillator1.wrapped().is_some(), increment);
+        let (oscillator1_left, oscillator1_right) = if self.unison_enabled() {
+            self.unison.tick()
+        } else {
+            (oscillator1, oscillator1)
+        };
+
+        if self.sync {
+            self.oscillator2.hard_sync(self.oscillator1.wrapped());
+        }
+        let oscillator2 = self.oscillator2.tick();
+        self.last_oscillator2 = oscillator2;
+        self.advance_pitch(1);
+        let amplitude = self.envelope1.process() * self.velocity_gain();
+
+        let mix = (self.oscillator_mix + breakpoint * self.breakpoint_mix).clamp(0.0, 1.0);
+        let left = (self.mix_oscillators(oscillator1_left, oscillator2, mix) + sub) * amplitude;
+        let right = (self.mix_oscillators(oscillator1_right, oscillator2, mix) + sub) * amplitude;
+
+        (self.filters[0].process(left), self.filters[1].process(right))
     }
 
-    pub fn note_on(&mut self, note_number: u8) {
+    /// Renders the next `left.len()` samples of the voice, adding them to the left and right buffers.
+    ///
+    /// Oscillators, envelopes and filters are rendered in chunks of up to four samples unless
+    /// FM, the sub-oscillator, unison or an oscillator type other than basic is enabled, the
+    /// block then falling back to [`Voice::process`]. Both render
+    /// the same samples up to float rounding.
+    pub fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) {
         if !self.active {
-            let frequency = self.midi_note_to_frequency(note_number);
+            return;
+        }
+
+        if !self.block_rendering() {
+            for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+                let (l, r) = self.process();
+                *left += l;
+                *right += r;
+            }
+            return;
+        }
+
+        // The scalar oscillators keep the phase between blocks.
+        for (block_oscillator, oscillator) in self.block_oscillators.iter_mut().zip([&self.oscillator1, &self.oscillator2]) {
+            block_oscillator.set_phase(oscillator.phase());
+        }
+
+        let mut start = 0;
+        while start < left.len() {
+            if self.envelope1.is_idle() {
+                self.active = false;
+                self.note_number = None;
+                break;
+            }
+
+            if self.follow_restart() {
+                for (block_oscillator, oscillator) in self.block_oscillators.iter_mut().zip([&self.oscillator1, &self.oscillator2]) {
+                    block_oscillator.set_phase(oscillator.phase());
+                }
+            }
+            let len = self.chunk_len(left.len() - start);
+            let (amplitude, len) = self.envelope1.process_x4(len);
+            let breakpoint = self.process_breakpoint_envelope_x4(len);
+            let filter_envelope = self.process_filter_envelope_x4(len, breakpoint);
+            let cutoff_mod = (filter_envelope * f32x4::splat(self.filter_envelope_depth())
+                + breakpoint * f32x4::splat(self.breakpoint_cutoff))
+                .to_array();
+
+            if self.pulse_width_modulated() {
+                let mut pulse_widths = [0.0; 4];
+                for (pulse_width, breakpoint) in pulse_widths[..len].iter_mut().zip(breakpoint.to_array()) {
+                    *pulse_width = self.pulse_width + breakpoint * self.breakpoint_pulse_width;
+                    if self.pwm_depth > 0.0 {
+                        *pulse_width += self.pwm_depth * self.pwm_lfo.process();
+                    }
+                }
+                let last = pulse_widths[len - 1];
+                pulse_widths[len..].fill(last);
+                self.set_pulse_width(last);
+                for oscillator in &mut self.block_oscillators {
+                    oscillator.set_pulse_width_x4(f32x4::from(pulse_widths));
+                }
+            }
+            let shape_amounts = f32x4::splat(self.shape_amount)
+                + filter_envelope * f32x4::splat(self.shape_envelope_amount)
+                + breakpoint * f32x4::splat(self.breakpoint_shape);
+            if self.shape_modulated() {
+                self.set_shape_amount(shape_amounts.to_array()[len - 1]);
+                for oscillator in &mut self.block_oscillators {
+                    oscillator.set_shape_amount_x4(shape_amounts);
+                }
+            }
+
+            let (oscillator1, wraps) = self.tick_block_oscillator(0, len, shape_amounts, None);
+            let (oscillator2, _) = self.tick_block_oscillator(1, len, shape_amounts, self.sync.then_some(wraps));
+            self.last_oscillator2 = oscillator2.to_array()[len - 1];
+            self.advance_pitch(len as u32);
+
+            let mix = (f32x4::splat(self.oscillator_mix) + breakpoint * f32x4::splat(self.breakpoint_mix))
+                .max(f32x4::ZERO)
+                .min(f32x4::ONE);
+            let ring_mod = f32x4::splat(self.ring_mod);
+            let mixed = oscillator1 * (f32x4::ONE - mix) + oscillator2 * mix;
+            let mixed = mixed * (f32x4::ONE - ring_mod) + oscillator1 * oscillator2 * ring_mod;
+            let samples = mixed * (amplitude * f32x4::splat(self.velocity_gain()));
+
+            let mut chunk_left = samples.to_array();
+            let mut chunk_right = chunk_left;
+            let [filter_left, filter_right] = &mut self.filters;
+            filter_left.process_stereo(filter_right, &mut chunk_left[..len], &mut chunk_right[..len], &cutoff_mod[..len]);
+            for (output, sample) in left[start..].iter_mut().zip(&chunk_left[..len]) {
+                *output += sample;
+            }
+            for (output, sample) in right[start..].iter_mut().zip(&chunk_right[..len]) {
+                *output += sample;
+            }
+            start += len;
+        }
+
+        for (oscillator, block_oscillator) in [&mut self.oscillator1, &mut self.oscillator2].into_iter().zip(&self.block_oscillators) {
+            if block_oscillator.waveform() != Waveform::Noise {
+                oscillator.set_phase(block_oscillator.phase());
+            }
+        }
+    }
 
-            self.oscillator1.set_frequency(frequency);
+    /// Starts the note with a velocity from 0.0 to 1.0, or plays it again on the sounding voice.
+    ///
+    /// A note played again takes on its velocity, at once when the envelopes continue from their
+    /// level and once the fade out is over when they restart.
+    pub fn note_on(&mut self, note_number: u8, velocity: f32) {
+        if !self.active {
+            self.pending_velocity = None;
+            self.set_velocity(velocity);
+            self.frequency = self.midi_note_to_frequency(note_number);
+            self.detune = [self.rng.next_bipolar() * self.random_detune, self.rng.next_bipolar() * self.random_detune];
+            self.update_frequencies();
+            self.unison.reset();
+            // Each voice sweeps its pulse width out of phase with the others.
+            self.pwm_lfo.set_phase(self.rng.next_f32());
+            // Free-running oscillators kept turning while the voice was silent.
+            for (oscillator, mode) in [&mut self.oscillator1, &mut self.oscillator2].into_iter().zip(self.phase_modes) {
+                match mode {
+                    PhaseMode::FreeRunning => oscillator.set_phase(self.rng.next_f32()),
+                    PhaseMode::Reset => oscillator.set_phase(0.0),
+                }
+            }
+            self.oscillator1.trigger();
+            self.oscillator2.trigger();
             self.envelope1.trigger();
             self.filter_envelope.trigger();
+            self.breakpoint_envelope.trigger();
             self.note_number = Some(note_number);
             self.active = true;
         } else {
-            self.envelope1.trigger();
-            self.filter_envelope.trigger();
+            if self.retrigger_mode == RetriggerMode::Legato && !self.envelope1.is_released() {
+                return;
+            }
+
+            match self.retrigger_mode {
+                // The oscillators start over once the fade out is over, see `Voice::follow_restart`.
+                RetriggerMode::Reset => {
+                    self.envelope1.restart();
+                    self.filter_envelope.restart();
+                    self.breakpoint_envelope.restart();
+                    self.pending_velocity = Some(velocity);
+                },
+                RetriggerMode::Continue | RetriggerMode::Legato => {
+                    self.set_velocity(velocity);
+                    self.retrigger_oscillators();
+                    self.envelope1.trigger();
+                    self.filter_envelope.trigger();
+                    self.breakpoint_envelope.trigger();
+                },
+            }
         }
     }
 
@@ -70,6 +451,7 @@ impl Voice {
         if self.active {
             self.envelope1.release();
             self.filter_envelope.release();
+            self.breakpoint_envelope.release();
         }
     }
 
@@ -77,33 +459,300 @@ impl Voice {
         match param {
             SynthParam::EnvelopeStage(stage, value) => {
                 match stage {
-                    EnvelopeStage::Attack => self.envelope1.set_attack_time(value),
+                    EnvelopeStage::Delay => self.envelope1.set_delay_time(value),
+                    EnvelopeStage::Attack => {
+                        self.attack_times[0] = value;
+                        self.update_attack_times();
+                    },
+                    EnvelopeStage::Hold => self.envelope1.set_hold_time(value),
                     EnvelopeStage::Decay => self.envelope1.set_decay_time(value),
                     EnvelopeStage::Sustain => self.envelope1.set_sustain_level(value),
                     EnvelopeStage::Release => self.envelope1.set_release_time(value),
                     EnvelopeStage::Idle => (),
                 }
             },
-            SynthParam::Waveform(waveform) => self.oscillator1.set_waveform(waveform),
-            SynthParam::FilterMode(mode) => self.filter.set_mode(mode),
-            SynthParam::Cutoff(value) => self.filter.set_cutoff(value),
-            SynthParam::Resonance(value) => self.filter.set_resonance(value),
+            SynthParam::EnvelopeCurve(stage, curve) => self.envelope1.set_curve(stage, curve),
+            SynthParam::RetriggerMode(mode) => self.retrigger_mode = mode,
+            SynthParam::Waveform(waveform) => {
+                self.oscillator1.set_waveform(waveform);
+                self.block_oscillators[0].set_waveform(waveform);
+                self.unison.set_waveform(waveform);
+            },
+            SynthParam::OscillatorQuality(quality) => {
+                self.oscillator1.set_quality(quality);
+                self.oscillator2.set_quality(quality);
+                self.unison.set_quality(quality);
+                self.sub.set_quality(quality);
+                for oscillator in &mut self.block_oscillators {
+                    oscillator.set_quality(quality);
+                }
+            },
+            SynthParam::NoiseColor(color) => {
+                self.oscillator1.set_noise_color(color);
+                self.oscillator2.set_noise_color(color);
+                self.unison.set_noise_color(color);
+                for oscillator in &mut self.block_oscillators {
+                    oscillator.set_noise_color(color);
+                }
+            },
+            SynthParam::OscillatorType(oscillator_type) => self.oscillator1.set_type(oscillator_type),
+            SynthParam::PhaseMode(mode) => self.phase_modes[0] = mode,
+            SynthParam::WavetablePosition(value) => {
+                self.oscillator1.set_wavetable_position(value);
+                self.oscillator2.set_wavetable_position(value);
+            },
+            SynthParam::PluckExcitation(excitation) => {
+                self.oscillator1.set_pluck_excitation(excitation);
+                self.oscillator2.set_pluck_excitation(excitation);
+            },
+            SynthParam::PluckDamping(value) => {
+                self.oscillator1.set_pluck_damping(value);
+                self.oscillator2.set_pluck_damping(value);
+            },
+            SynthParam::PluckBrightness(value) => {
+                self.oscillator1.set_pluck_brightness(value);
+                self.oscillator2.set_pluck_brightness(value);
+            },
+            SynthParam::PluckDecay(seconds) => {
+                self.oscillator1.set_pluck_decay(seconds);
+                self.oscillator2.set_pluck_decay(seconds);
+            },
+            SynthParam::SampleInterpolation(interpolation) => {
+                self.oscillator1.set_sample_interpolation(interpolation);
+                self.oscillator2.set_sample_interpolation(interpolation);
+            },
+            SynthParam::SampleLoopMode(loop_mode) => {
+                self.oscillator1.set_sample_loop_mode(loop_mode);
+                self.oscillator2.set_sample_loop_mode(loop_mode);
+            },
+            SynthParam::SampleLoopStart(value) => {
+                self.oscillator1.set_sample_loop_start(value);
+                self.oscillator2.set_sample_loop_start(value);
+            },
+            SynthParam::SampleLoopEnd(value) => {
+                self.oscillator1.set_sample_loop_end(value);
+                self.oscillator2.set_sample_loop_end(value);
+            },
+            SynthParam::SampleCrossfade(value) => {
+                self.oscillator1.set_sample_crossfade(value);
+                self.oscillator2.set_sample_crossfade(value);
+            },
+            SynthParam::SampleRootKey(key) => {
+                self.oscillator1.set_sample_root_key(key);
+                self.oscillator2.set_sample_root_key(key);
+            },
+            SynthParam::SampleFineTune(cents) => {
+                self.oscillator1.set_sample_fine_tune(cents);
+                self.oscillator2.set_sample_fine_tune(cents);
+            },
+            SynthParam::AdditivePartials(count) => {
+                self.oscillator1.set_additive_partials(count);
+                self.oscillator2.set_additive_partials(count);
+            },
+            SynthParam::AdditiveBrightness(value) => {
+                self.oscillator1.set_additive_brightness(value);
+                self.oscillator2.set_additive_brightness(value);
+            },
+            SynthParam::AdditiveOddEven(value) => {
+                self.oscillator1.set_additive_odd_even(value);
+                self.oscillator2.set_additive_odd_even(value);
+            },
+            SynthParam::AdditiveStretch(value) => {
+                self.oscillator1.set_additive_stretch(value);
+                self.oscillator2.set_additive_stretch(value);
+            },
+            SynthParam::Oscillator2Waveform(waveform) => {
+                self.oscillator2.set_waveform(waveform);
+                self.block_oscillators[1].set_waveform(waveform);
+            },
+            SynthParam::Oscillator2Type(oscillator_type) => self.oscillator2.set_type(oscillator_type),
+            SynthParam::Oscillator2PhaseMode(mode) => self.phase_modes[1] = mode,
+            SynthParam::Oscillator2Octave(octave) => {
+                self.oscillator2_octave = octave.clamp(-4, 4);
+                self.update_frequencies();
+            },
+            SynthParam::Oscillator2Semitone(semitone) => {
+                self.oscillator2_semitone = semitone.clamp(-12, 12);
+                self.update_frequencies();
+            },
+            SynthParam::Oscillator2Fine(cents) => {
+                self.oscillator2_fine = cents.clamp(-100.0, 100.0);
+                self.update_frequencies();
+            },
+            SynthParam::OscillatorMix(value) => self.oscillator_mix = value.clamp(0.0, 1.0),
+            SynthParam::SubWaveform(waveform) => self.sub.set_waveform(waveform),
+            SynthParam::SubOctave(octave) => self.sub.set_octave(octave),
+            SynthParam::SubLevel(value) => self.sub_level = value.clamp(0.0, 1.0),
+            SynthParam::OscillatorSync(sync) => {
+                self.sync = sync;
+                self.update_frequencies();
+            },
+            SynthParam::SyncPitch(semitones) => {
+                self.sync_pitch = semitones.clamp(0.0, 48.0);
+                self.update_frequencies();
+            },
+            SynthParam::SyncEnvAmount(semitones) => {
+                self.sync_envelope_amount = semitones.clamp(-48.0, 48.0);
+                self.update_frequencies();
+            },
+            SynthParam::FmMode(mode) => self.fm_mode = mode,
+            SynthParam::FmIndex(value) => self.fm_index = value.clamp(0.0, 16.0),
+            SynthParam::RingMod(value) => self.ring_mod = value.clamp(0.0, 1.0),
+            SynthParam::PulseWidth(value) => {
+                self.pulse_width = value.clamp(0.01, 0.99);
+                self.set_pulse_width(self.pulse_width);
+            },
+            SynthParam::PwmDepth(value) => {
+                self.pwm_depth = value.clamp(0.0, 0.49);
+                self.set_pulse_width(self.pulse_width);
+            },
+            SynthParam::PwmRate(rate) => self.pwm_lfo.set_rate(rate),
+            SynthParam::ShapeMode(mode) => {
+                self.oscillator1.set_shape(mode);
+                self.oscillator2.set_shape(mode);
+                self.unison.set_shape(mode);
+                for oscillator in &mut self.block_oscillators {
+                    oscillator.set_shape(mode);
+                }
+            },
+            SynthParam::ShapeAmount(value) => {
+                self.shape_amount = value.clamp(0.0, 1.0);
+                self.set_shape_amount(self.shape_amount);
+            },
+            SynthParam::ShapeEnvAmount(value) => {
+                self.shape_envelope_amount = value.clamp(-1.0, 1.0);
+                self.set_shape_amount(self.shape_amount);
+            },
+            SynthParam::DriftAmount(cents) => {
+                self.drift_amount = cents.clamp(0.0, 50.0);
+                self.update_frequencies();
+            },
+            SynthParam::DriftRate(rate) => {
+                for drift in &mut self.drift {
+                    drift.set_rate(rate);
+                }
+            },
+            SynthParam::RandomDetune(cents) => self.random_detune = cents.clamp(0.0, 50.0),
+            SynthParam::UnisonVoices(voices) => self.unison.set_voices(voices),
+            SynthParam::UnisonDetune(cents) => self.unison.set_detune(cents),
+            SynthParam::UnisonDetuneCurve(value) => self.unison.set_detune_curve(value),
+            SynthParam::UnisonRandomPhase(value) => self.unison.set_random_phase(value),
+            SynthParam::UnisonStereoSpread(value) => self.unison.set_stereo_spread(value),
+            SynthParam::FilterMode(mode) => {
+                for filter in &mut self.filters {
+                    filter.set_mode(mode);
+                }
+            },
+            SynthParam::Cutoff(value) => {
+                for filter in &mut self.filters {
+                    filter.set_cutoff(value);
+                }
+            },
+            SynthParam::Resonance(value) => {
+                for filter in &mut self.filters {
+                    filter.set_resonance(value);
+                }
+            },
+            SynthParam::FilterGain(gain) => {
+                for filter in &mut self.filters {
+                    filter.set_gain(gain);
+                }
+            },
             SynthParam::SampleRate(rate) => {
                 self.oscillator1.set_sample_rate(rate);
+                self.oscillator2.set_sample_rate(rate);
+                self.unison.set_sample_rate(rate);
+                for oscillator in &mut self.block_oscillators {
+                    oscillator.set_sample_rate(rate);
+                }
+                self.pwm_lfo.set_sample_rate(rate);
+                for drift in &mut self.drift {
+                    drift.set_sample_rate(rate);
+                }
+                for filter in &mut self.filters {
+                    filter.set_sample_rate(rate);
+                }
                 self.envelope1.set_sample_rate(rate);
+                self.filter_envelope.set_sample_rate(rate);
+                self.breakpoint_envelope.set_sample_rate(rate);
             },
             SynthParam::FilterEnvAmount(value) => {
                 self.filter_envelope_amount = value.clamp(-1.0, 1.0);
             },
             SynthParam::FilterEnvStage(stage, value) => {
                 match stage {
-                    EnvelopeStage::Attack => self.filter_envelope.set_attack_time(value),
+                    EnvelopeStage::Delay => self.filter_envelope.set_delay_time(value),
+                    EnvelopeStage::Attack => {
+                        self.attack_times[1] = value;
+                        self.update_attack_times();
+                    },
+                    EnvelopeStage::Hold => self.filter_envelope.set_hold_time(value),
                     EnvelopeStage::Decay => self.filter_envelope.set_decay_time(value),
                     EnvelopeStage::Sustain => self.filter_envelope.set_sustain_level(value),
                     EnvelopeStage::Release => self.filter_envelope.set_release_time(value),
                     EnvelopeStage::Idle => (),
                 }
             },
+            SynthParam::FilterEnvCurve(stage, curve) => self.filter_envelope.set_curve(stage, curve),
+            SynthParam::FilterEnvType(envelope_type) => self.filter_envelope_type = envelope_type,
+            SynthParam::BreakpointPitch(semitones) => {
+                self.breakpoint_pitch = semitones.clamp(-48.0, 48.0);
+                self.update_frequencies();
+            },
+            SynthParam::BreakpointCutoff(value) => self.breakpoint_cutoff = value.clamp(-1.0, 1.0),
+            SynthParam::BreakpointMix(value) => self.breakpoint_mix = value.clamp(-1.0, 1.0),
+            SynthParam::BreakpointPulseWidth(value) => {
+                self.breakpoint_pulse_width = value.clamp(-0.49, 0.49);
+                self.set_pulse_width(self.pulse_width);
+            },
+            SynthParam::BreakpointShape(value) => {
+                self.breakpoint_shape = value.clamp(-1.0, 1.0);
+                self.set_shape_amount(self.shape_amount);
+            },
+            SynthParam::VelocityCurve(curve) => self.velocity_curve = curve,
+            SynthParam::VelocityAmplitude(amount) => self.velocity_amplitude = amount.clamp(0.0, 1.0),
+            SynthParam::VelocityFilterEnv(amount) => self.velocity_filter_envelope = amount.clamp(0.0, 1.0),
+            SynthParam::VelocityAttack(amount) => {
+                self.velocity_attack = amount.clamp(0.0, 1.0);
+                self.update_attack_times();
+            },
+        }
+    }
+
+    pub fn set_wavetable(&mut self, table: Arc<Wavetable>) {
+        self.oscillator1.set_wavetable(table.clone());
+        self.oscillator2.set_wavetable(table);
+    }
+
+    pub fn set_sample(&mut self, sample: Arc<Sample>) {
+        self.oscillator1.set_sample(sample.clone());
+        self.oscillator2.set_sample(sample);
+    }
+
+    pub fn set_additive_amplitudes(&mut self, amplitudes: &[f32]) {
+        self.oscillator1.set_additive_amplitudes(amplitudes);
+        self.oscillator2.set_additive_amplitudes(amplitudes);
+    }
+
+    /// Sets the breakpoints, sustain point and loop of the breakpoint envelope, see
+    /// [`BreakpointEnvelope::set_breakpoints`].
+    pub fn set_breakpoints(&mut self, breakpoints: &[Breakpoint], sustain: Option<usize>, loop_points: Option<(usize, usize)>) {
+        self.breakpoint_envelope.set_breakpoints(breakpoints, sustain, loop_points);
+    }
+
+    /// Restarts every random generator of the voice from the given seed, making renders reproducible.
+    pub fn set_seed(&mut self, seed: u32) {
+        // Every generator draws its own seed, none repeating the stream of another.
+        let mut seeds = Rng::new(seed);
+        self.rng.seed(seeds.next_u32());
+        self.oscillator1.set_seed(seeds.next_u32());
+        self.oscillator2.set_seed(seeds.next_u32());
+        self.unison.set_seed(seeds.next_u32());
+        for oscillator in &mut self.block_oscillators {
+            oscillator.set_seed(seeds.next_u32());
+        }
+        for drift in &mut self.drift {
+            drift.seed(seeds.next_u32());
         }
     }
 
@@ -117,14 +766,622 @@ impl Voice {
 
     pub fn reset(&mut self) {
         self.note_number = None;
+        self.pending_velocity = None;
         self.active = false;
         self.oscillator1.reset();
+        self.oscillator2.reset();
+        self.sub.reset();
+        self.last_oscillator2 = 0.0;
+        self.unison.reset();
         self.envelope1.reset();
         self.filter_envelope.reset();
-        self.filter.reset();
+        self.breakpoint_envelope.reset();
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+
+    fn set_pulse_width(&mut self, pulse_width: f32) {
+        self.oscillator1.set_pulse_width(pulse_width);
+        self.oscillator2.set_pulse_width(pulse_width);
+        self.unison.set_pulse_width(pulse_width);
+        for oscillator in &mut self.block_oscillators {
+            oscillator.set_pulse_width(pulse_width);
+        }
+    }
+
+    fn set_shape_amount(&mut self, amount: f32) {
+        self.oscillator1.set_shape_amount(amount);
+        self.oscillator2.set_shape_amount(amount);
+        self.unison.set_shape_amount(amount);
+        for oscillator in &mut self.block_oscillators {
+            oscillator.set_shape_amount(amount);
+        }
+    }
+
+    /// Sets the velocity of the note from 0.0 to 1.0 and scales the attack times to it.
+    fn set_velocity(&mut self, velocity: f32) {
+        self.velocity = self.velocity_curve.apply(velocity);
+        self.update_attack_times();
+    }
+
+    /// Takes on the velocity of a note played again and starts the oscillators over once the
+    /// restart fade out is over, so the phase reset is not heard. Returns whether it did.
+    fn follow_restart(&mut self) -> bool {
+        if !self.envelope1.is_restarting()
+            && let Some(velocity) = self.pending_velocity.take()
+        {
+            self.set_velocity(velocity);
+            self.retrigger_oscillators();
+            return true;
+        }
+
+        false
+    }
+
+    /// Starts the oscillators of a note played again, those in reset phase mode from phase 0.0.
+    fn retrigger_oscillators(&mut self) {
+        for (oscillator, mode) in [&mut self.oscillator1, &mut self.oscillator2].into_iter().zip(self.phase_modes) {
+            if mode == PhaseMode::Reset {
+                oscillator.set_phase(0.0);
+            }
+        }
+        self.oscillator1.trigger();
+        self.oscillator2.trigger();
+    }
+
+    /// Shortens the attack times of both envelopes for hard notes, a full velocity at full
+    /// amount attacking at once.
+    fn update_attack_times(&mut self) {
+        let scale = 1.0 - self.velocity_attack * self.velocity;
+        self.envelope1.set_attack_time(self.attack_times[0] * scale);
+        self.filter_envelope.set_attack_time(self.attack_times[1] * scale);
+    }
+
+    /// Amplitude scaling of the note by its velocity.
+    fn velocity_gain(&self) -> f32 {
+        1.0 - self.velocity_amplitude * (1.0 - self.velocity)
+    }
+
+    /// Filter envelope amount scaled by the note velocity.
+    fn filter_envelope_depth(&self) -> f32 {
+        self.filter_envelope_amount * (1.0 - self.velocity_filter_envelope * (1.0 - self.velocity))
+    }
+
+    /// Whether the breakpoint envelope runs, as filter envelope or modulating a destination of its own.
+    fn breakpoint_envelope_used(&self) -> bool {
+        self.filter_envelope_type == EnvelopeType::Breakpoint
+            || self.breakpoint_pitch != 0.0
+            || self.breakpoint_cutoff != 0.0
+            || self.breakpoint_mix != 0.0
+            || self.breakpoint_pulse_width != 0.0
+            || self.breakpoint_shape != 0.0
+    }
+
+    /// Advances the breakpoint envelope when it is used and returns its level.
+    fn process_breakpoint_envelope(&mut self) -> f32 {
+        if self.breakpoint_envelope_used() {
+            self.breakpoint_envelope.process()
+        } else {
+            self.breakpoint_envelope.level()
+        }
+    }
+
+    /// Advances the breakpoint envelope by `samples`, at most four, when it is used and returns
+    /// their levels.
+    fn process_breakpoint_envelope_x4(&mut self, samples: usize) -> f32x4 {
+        if self.breakpoint_envelope_used() {
+            self.breakpoint_envelope.process_x4(samples)
+        } else {
+            f32x4::splat(self.breakpoint_envelope.level())
+        }
+    }
+
+    /// Advances the envelope selected as filter envelope and returns its level, `breakpoint`
+    /// being the level of the breakpoint envelope.
+    fn process_filter_envelope(&mut self, breakpoint: f32) -> f32 {
+        match self.filter_envelope_type {
+            EnvelopeType::Adsr => self.filter_envelope.process(),
+            EnvelopeType::Breakpoint => breakpoint,
+        }
+    }
+
+    /// Advances the envelope selected as filter envelope by `samples`, at most four, and
+    /// returns their levels, `breakpoint` being the levels of the breakpoint envelope.
+    fn process_filter_envelope_x4(&mut self, samples: usize, breakpoint: f32x4) -> f32x4 {
+        match self.filter_envelope_type {
+            EnvelopeType::Adsr => self.filter_envelope.process_x4(samples).0,
+            EnvelopeType::Breakpoint => breakpoint,
+        }
+    }
+
+    /// Whether the pulse width follows the PWM LFO or the breakpoint envelope.
+    fn pulse_width_modulated(&self) -> bool {
+        self.pwm_depth > 0.0 || self.breakpoint_pulse_width != 0.0
+    }
+
+    /// Whether the shape amount follows the filter or breakpoint envelope.
+    fn shape_modulated(&self) -> bool {
+        self.shape_envelope_amount != 0.0 || self.breakpoint_shape != 0.0
+    }
+
+    /// Length of the next chunk of [`Voice::process_block`], at most `remaining` samples.
+    ///
+    /// A chunk ends where the modulated frequencies are updated, and holds a single sample until
+    /// the velocity of a restarted note is taken on.
+    fn chunk_len(&self, remaining: usize) -> usize {
+        let mut len = remaining.min(4);
+        if self.pitch_modulated() {
+            len = len.min((PITCH_INTERVAL - self.since_pitch) as usize);
+        }
+        if self.pending_velocity.is_some() {
+            len = 1;
+        }
+
+        len
+    }
+
+    /// Renders `samples` samples of oscillator 1 (`index` 0) or 2, at most four, shaped by
+    /// `shape_amounts` under the shape envelope and hard-synced to the wraps of `sync`, and
+    /// returns them with the wraps of their phase.
+    ///
+    /// Noise is rendered by the scalar oscillator, so both paths share its noise state.
+    fn tick_block_oscillator(
+        &mut self,
+        index: usize,
+        samples: usize,
+        shape_amounts: f32x4,
+        sync: Option<[Option<f32>; 4]>,
+    ) -> (f32x4, [Option<f32>; 4]) {
+        let block_oscillator = &mut self.block_oscillators[index];
+        if block_oscillator.waveform() != Waveform::Noise {
+            if let Some(wraps) = sync {
+                block_oscillator.hard_sync(wraps);
+            }
+            let output = block_oscillator.tick_samples(samples);
+            return (output, block_oscillator.wrapped());
+        }
+
+        let shape_modulated = self.shape_modulated();
+        let oscillator = if index == 0 { &mut self.oscillator1 } else { &mut self.oscillator2 };
+        let mut noise = [0.0; 4];
+        let mut wraps = [None; 4];
+        for lane in 0..samples {
+            if shape_modulated {
+                oscillator.set_shape_amount(shape_amounts.to_array()[lane]);
+            }
+            if let Some(sync) = sync {
+                oscillator.hard_sync(sync[lane]);
+            }
+            noise[lane] = oscillator.tick();
+            wraps[lane] = oscillator.wrapped();
+        }
+
+        (f32x4::from(noise), wraps)
+    }
+
+    /// Level of the envelope selected as filter envelope.
+    fn filter_envelope_level(&self) -> f32 {
+        match self.filter_envelope_type {
+            EnvelopeType::Adsr => self.filter_envelope.state().1,
+            EnvelopeType::Breakpoint => self.breakpoint_envelope.level(),
+        }
+    }
+
+    /// Modulates the cutoff of both filters, the right one copying the biquad coefficients of
+    /// the left one.
+    fn set_cutoff_mod(&mut self, cutoff_mod: f32) {
+        let [left, right] = &mut self.filters;
+        left.set_cutoff_mod(cutoff_mod);
+        right.follow(left);
+    }
+
+    fn mix_oscillators(&self, oscillator1: f32, oscillator2: f32, mix: f32) -> f32 {
+        let mix = oscillator1 * (1.0 - mix) + oscillator2 * mix;
+        mix * (1.0 - self.ring_mod) + oscillator1 * oscillator2 * self.ring_mod
+    }
+
+    /// Unison stacks the basic waveforms of oscillator 1.
+    fn unison_enabled(&self) -> bool {
+        self.unison.voices() > 1 && self.oscillator1.oscillator_type() == OscillatorType::Basic
+    }
+
+    /// Packs the voice state for the voice bank, or returns `None` if the voice needs
+    /// per-voice modulation the bank does not render (see [`Voice::process_block`], hard sync, PWM,
+    /// shape envelope, curved, delayed, held or breakpoint envelopes, the fade out of a restart
+    /// until the velocity of the restarted note is taken on and the biquad filter modes).
+    pub(crate) fn lane(&self) -> Option<VoiceLane> {
+        if !self.block_rendering()
+            || self.sync
+            || self.pulse_width_modulated()
+            || self.shape_modulated()
+            || self.breakpoint_envelope_used()
+            || !self.envelope1.is_linear_adsr()
+            || !self.filter_envelope.is_linear_adsr()
+            || self.envelope1.is_restarting()
+            || self.filter_envelope.is_restarting()
+            || self.pending_velocity.is_some()
+            || self.filters[0].mode().is_biquad()
+        {
+            return None;
+        }
+
+        Some(VoiceLane {
+            phases: [self.oscillator1.phase(), self.oscillator2.phase()],
+            frequencies: [self.oscillator1_frequency, self.oscillator2_frequency],
+            oscillator_mix: self.oscillator_mix,
+            ring_mod: self.ring_mod,
+            last_oscillator2: self.last_oscillator2,
+            envelopes: [self.envelope1.state(), self.filter_envelope.state()],
+            envelope_steps: [self.envelope1.steps(), self.filter_envelope.steps()],
+            sustain_levels: [self.envelope1.sustain_level(), self.filter_envelope.sustain_level()],
+            filter_envelope_amount: self.filter_envelope_depth(),
+            gain: self.velocity_gain(),
+            filter_mode: self.filters[0].mode(),
+            cutoff: self.filters[0].cutoff(),
+            filters: [self.filters[0].state(), self.filters[1].state()],
+        })
+    }
+
+    /// Restores the state rendered by the voice bank, releasing the voice once its envelope is idle.
+    pub(crate) fn set_lane(&mut self, lane: &VoiceLane) {
+        self.oscillator1.set_phase(lane.phases[0]);
+        self.oscillator2.set_phase(lane.phases[1]);
+        self.last_oscillator2 = lane.last_oscillator2;
+        let [(stage, level), (filter_stage, filter_level)] = lane.envelopes;
+        self.envelope1.set_state(stage, level);
+        self.filter_envelope.set_state(filter_stage, filter_level);
+        for (filter, state) in self.filters.iter_mut().zip(lane.filters) {
+            filter.set_state(state);
+        }
+
+        if self.envelope1.is_idle() {
+            self.active = false;
+            self.note_number = None;
+        }
+    }
+
+    /// Whether [`Voice::process_block`] can render the oscillators four samples at a time.
+    fn block_rendering(&self) -> bool {
+        self.oscillator1.oscillator_type() == OscillatorType::Basic
+            && self.oscillator2.oscillator_type() == OscillatorType::Basic
+            && self.fm_mode == FmMode::Off
+            && self.sub_level == 0.0
+            && !self.unison_enabled()
+    }
+
+    /// Advances the pitch modulation by `samples` and returns `true` if the frequencies were updated.
+    ///
+    /// The frequencies follow the drift, the sync envelope and the breakpoint envelope every
+    /// [`PITCH_INTERVAL`] samples.
+    pub(crate) fn advance_pitch(&mut self, samples: u32) -> bool {
+        if !self.pitch_modulated() {
+            return false;
+        }
+
+        if self.drift_amount != 0.0 {
+            for drift in &mut self.drift {
+                for _ in 0..samples {
+                    drift.process();
+                }
+            }
+        }
+
+        self.since_pitch += samples;
+        if self.since_pitch < PITCH_INTERVAL {
+            return false;
+        }
+        self.since_pitch = 0;
+        self.update_frequencies();
+
+        true
+    }
+
+    /// Whether the drift or the sync envelope modulates the oscillator frequencies.
+    fn pitch_modulated(&self) -> bool {
+        self.drift_amount != 0.0 || (self.sync && self.sync_envelope_amount != 0.0) || self.breakpoint_pitch != 0.0
+    }
+
+    fn update_frequencies(&mut self) {
+        // Cents of detune and drift of each oscillator.
+        let [cents1, cents2] = [0, 1].map(|index| self.detune[index] + self.drift_amount * self.drift[index].value());
+        // Semitones of the breakpoint envelope on both oscillators.
+        let bend = self.breakpoint_envelope.level() * self.breakpoint_pitch;
+        let mut offset = (self.oscillator2_octave * 12 + self.oscillator2_semitone) as f32
+            + (self.oscillator2_fine + cents2) / 100.0
+            + bend;
+        if self.sync {
+            offset += self.sync_pitch + self.filter_envelope_level() * self.sync_envelope_amount;
+        }
+        self.oscillator1_frequency = self.frequency * 2.0_f32.powf(cents1 / 1200.0 + bend / 12.0);
+        self.oscillator2_frequency = self.frequency * 2.0_f32.powf(offset / 12.0);
+
+        self.oscillator1.set_frequency(self.oscillator1_frequency);
+        self.unison.set_frequency(self.oscillator1_frequency);
+        self.oscillator2.set_frequency(self.oscillator2_frequency);
+        self.block_oscillators[0].set_frequency(self.oscillator1_frequency);
+        self.block_oscillators[1].set_frequency(self.oscillator2_frequency);
     }
 
     fn midi_note_to_frequency(&self, note_number: u8) -> f32 {
         440.0 * 2.0_f32.powf((note_number as f32 - 69.0) / 12.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oscillator::{amplitude_at, NoiseColor, Waveform};
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    /// A5, the frequency of oscillator 1.
+    const CARRIER: f32 = 880.0;
+    /// Frequency of oscillator 2, placing the sidebands on multiples of 40 Hz without any
+    /// sideband folding back below 0 Hz onto another.
+    const MODULATOR: f32 = 320.0;
+
+    /// Renders 0.1 s of a sustained A5 with two sine oscillators, after the filter settles.
+    fn render(params: &[SynthParam]) -> Vec<f32> {
+        let mut voice = Voice::new(SAMPLE_RATE);
+        for param in [
+            SynthParam::Waveform(Waveform::Sine),
+            SynthParam::Oscillator2Waveform(Waveform::Sine),
+            SynthParam::Oscillator2Octave(-1),
+            SynthParam::Oscillator2Semitone(-5),
+            SynthParam::Oscillator2Fine(1200.0 * (MODULATOR / CARRIER).log2() + 1700.0),
+            SynthParam::EnvelopeStage(EnvelopeStage::Attack, 0.0),
+            SynthParam::EnvelopeStage(EnvelopeStage::Sustain, 1.0),
+            SynthParam::Cutoff(1.0),
+        ].iter().chain(params) {
+            voice.apply_param(*param);
+        }
+        voice.note_on(81, 1.0);
+
+        (0..9600).map(|_| voice.process().0).skip(4800).collect()
+    }
+
+    /// Asserts the amplitude of each sideband of the carrier, `n` modulator frequencies away.
+    fn assert_sidebands(samples: &[f32], sidebands: &[(i32, f32)]) {
+        for &(n, expected) in sidebands {
+            let frequency = (CARRIER + n as f32 * MODULATOR).abs();
+            let amplitude = amplitude_at(samples, frequency, SAMPLE_RATE);
+            assert!((amplitude - expected).abs() < 0.01, "{frequency} Hz: {amplitude}, expected {expected}");
+        }
+    }
+
+    /// A detuned sawtooth and square through a swept filter, changed by `params` and rendered
+    /// from note-on through the release.
+    fn render_note(params: &[SynthParam], block_len: Option<usize>) -> Vec<f32> {
+        let mut voice = Voice::new(SAMPLE_RATE);
+        let breakpoints = [Breakpoint::new(0.02, 1.0, 0.5), Breakpoint::new(0.03, 0.2, -0.3), Breakpoint::new(0.02, 0.7, 0.0)];
+        voice.set_breakpoints(&breakpoints, None, Some((0, 2)));
+        for param in [
+            SynthParam::Waveform(Waveform::Sawtooth),
+            SynthParam::Oscillator2Waveform(Waveform::Square),
+            SynthParam::Oscillator2Fine(7.0),
+            SynthParam::OscillatorMix(0.5),
+            SynthParam::PhaseMode(PhaseMode::Reset),
+            SynthParam::Oscillator2PhaseMode(PhaseMode::Reset),
+            SynthParam::EnvelopeStage(EnvelopeStage::Attack, 0.01),
+            SynthParam::EnvelopeStage(EnvelopeStage::Decay, 0.05),
+            SynthParam::EnvelopeStage(EnvelopeStage::Sustain, 0.6),
+            SynthParam::EnvelopeStage(EnvelopeStage::Release, 0.05),
+            SynthParam::Cutoff(0.4),
+            SynthParam::FilterEnvAmount(0.5),
+        ].iter().chain(params) {
+            voice.apply_param(*param);
+        }
+        voice.note_on(57, 1.0);
+
+        let mut left = vec![0.0; 9600];
+        let mut right = vec![0.0; 9600];
+        for (half, release) in [(0..4800, true), (4800..9600, false)] {
+            match block_len {
+                Some(len) => {
+                    for (left, right) in left[half.clone()].chunks_mut(len).zip(right[half].chunks_mut(len)) {
+                        voice.process_block(left, right);
+                    }
+                },
+                None => {
+                    for index in half {
+                        (left[index], right[index]) = voice.process();
+                    }
+                },
+            }
+            if release {
+                voice.note_off(57);
+            }
+        }
+
+        left.into_iter().chain(right).collect()
+    }
+
+    #[test]
+    fn process_block_matches_process() {
+        let patches: [&[SynthParam]; 9] = [
+            &[],
+            &[SynthParam::PwmDepth(0.3), SynthParam::PwmRate(7.0)],
+            &[
+                SynthParam::Waveform(Waveform::Sine),
+                SynthParam::ShapeMode(ShapeMode::PhaseDistortion),
+                SynthParam::ShapeAmount(0.3),
+                SynthParam::ShapeEnvAmount(0.6),
+            ],
+            &[SynthParam::ShapeMode(ShapeMode::Wavefolder), SynthParam::ShapeAmount(0.2), SynthParam::ShapeEnvAmount(0.5)],
+            &[SynthParam::ShapeMode(ShapeMode::Waveshaper), SynthParam::ShapeEnvAmount(0.8)],
+            &[
+                SynthParam::Waveform(Waveform::Noise),
+                SynthParam::NoiseColor(NoiseColor::Pink),
+                SynthParam::DriftAmount(20.0),
+                SynthParam::DriftRate(5.0),
+            ],
+            &[SynthParam::FilterMode(FilterMode::BiquadLowpass), SynthParam::Resonance(0.4)],
+            &[SynthParam::OscillatorSync(true), SynthParam::SyncPitch(7.0), SynthParam::SyncEnvAmount(12.0)],
+            &[
+                SynthParam::ShapeMode(ShapeMode::Wavefolder),
+                SynthParam::BreakpointPitch(3.0),
+                SynthParam::BreakpointCutoff(0.3),
+                SynthParam::BreakpointMix(-0.4),
+                SynthParam::BreakpointPulseWidth(0.2),
+                SynthParam::BreakpointShape(0.5),
+            ],
+        ];
+        for params in patches {
+            let samples = render_note(params, None);
+            // Odd block lengths leave a tail shorter than a SIMD vector.
+            for block_len in [1, 37, 64, 512] {
+                let difference = render_note(params, Some(block_len))
+                    .iter()
+                    .zip(&samples)
+                    .fold(0.0_f32, |difference, (block, sample)| difference.max((block - sample).abs()));
+                // Only the vectorised sine rounds differently from the scalar one.
+                assert!(difference < 1e-6, "{params:?}, block length {block_len}: {difference}");
+            }
+        }
+    }
+
+    /// Renders 0.1 s of pink noise from a voice seeded with `seed`.
+    fn render_noise(seed: u32) -> Vec<f32> {
+        let mut voice = Voice::new(SAMPLE_RATE);
+        voice.apply_param(SynthParam::Waveform(Waveform::Noise));
+        voice.apply_param(SynthParam::NoiseColor(NoiseColor::Pink));
+        voice.set_seed(seed);
+        voice.note_on(60, 1.0);
+
+        (0..4800).map(|_| voice.process().0).collect()
+    }
+
+    #[test]
+    fn retriggered_note_restarts_without_clicks() {
+        for block_len in [1, 64] {
+            let mut voice = Voice::new(SAMPLE_RATE);
+            for param in [
+                SynthParam::Waveform(Waveform::Sine),
+                SynthParam::Oscillator2Waveform(Waveform::Sine),
+                SynthParam::PhaseMode(PhaseMode::Reset),
+                SynthParam::Oscillator2PhaseMode(PhaseMode::Reset),
+                SynthParam::EnvelopeStage(EnvelopeStage::Attack, 0.0),
+                SynthParam::EnvelopeStage(EnvelopeStage::Sustain, 1.0),
+                SynthParam::Cutoff(1.0),
+            ] {
+                voice.apply_param(param);
+            }
+            voice.note_on(69, 1.0);
+
+            let mut left = vec![0.0; 4096];
+            let mut right = vec![0.0; 4096];
+            // Plays the note again a sixth of a cycle past the phase reset, near the peak.
+            for (start, chunk) in (0..left.len()).step_by(block_len).zip(left.chunks_mut(block_len)) {
+                if start == 1000 {
+                    voice.note_on(69, 1.0);
+                }
+                voice.process_block(chunk, &mut right[start..start + chunk.len()]);
+            }
+
+            // A 440 Hz sine at full level moves at most 0.058 per sample.
+            let largest_step = left.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f32::max);
+            assert!(largest_step < 0.08, "{block_len}-sample blocks: step of {largest_step}");
+        }
+    }
+
+    #[test]
+    fn continued_note_takes_on_the_new_velocity() {
+        for mode in [RetriggerMode::Continue, RetriggerMode::Legato] {
+            let mut voice = Voice::new(SAMPLE_RATE);
+            for param in [
+                SynthParam::Waveform(Waveform::Sine),
+                SynthParam::Oscillator2Waveform(Waveform::Sine),
+                SynthParam::RetriggerMode(mode),
+                SynthParam::VelocityAmplitude(1.0),
+                SynthParam::EnvelopeStage(EnvelopeStage::Attack, 0.0),
+                SynthParam::EnvelopeStage(EnvelopeStage::Sustain, 1.0),
+                SynthParam::EnvelopeStage(EnvelopeStage::Release, 1.0),
+                SynthParam::Cutoff(1.0),
+            ] {
+                voice.apply_param(param);
+            }
+            voice.note_on(69, 1.0);
+            let loud = (0..4800).map(|_| voice.process().0.abs()).fold(0.0, f32::max);
+            // Legato only plays a released note again.
+            voice.note_off(69);
+            voice.note_on(69, 0.25);
+            let soft = (0..4800).map(|_| voice.process().0.abs()).fold(0.0, f32::max);
+
+            assert!((soft / loud - 0.25).abs() < 0.01, "{mode:?}: {soft} against {loud}");
+        }
+    }
+
+    #[test]
+    fn automated_decay_keeps_the_envelope_continuous() {
+        let mut voice = Voice::new(SAMPLE_RATE);
+        for param in [
+            SynthParam::Waveform(Waveform::Sine),
+            SynthParam::Oscillator2Waveform(Waveform::Sine),
+            SynthParam::EnvelopeStage(EnvelopeStage::Attack, 0.0),
+            SynthParam::EnvelopeStage(EnvelopeStage::Decay, 0.2),
+            SynthParam::EnvelopeStage(EnvelopeStage::Sustain, 0.2),
+            SynthParam::Cutoff(1.0),
+        ] {
+            voice.apply_param(param);
+        }
+        voice.note_on(69, 1.0);
+        // The attack jumps to the peak at once.
+        voice.process();
+
+        let mut level = voice.envelope1.state().1;
+        let mut largest_step: f32 = 0.0;
+        // Sweeps the decay time up and down every 64 samples while the key is held.
+        for block in 0..150 {
+            let decay = if block % 2 == 0 { 0.01 } else { 2.0 };
+            voice.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Decay, decay * (1.0 + block as f32 / 50.0)));
+            for _ in 0..64 {
+                voice.process();
+                let next = voice.envelope1.state().1;
+                largest_step = largest_step.max((next - level).abs());
+                level = next;
+            }
+        }
+
+        // The fastest decay falls 0.8 over 480 samples.
+        assert!(largest_step < 0.002, "step of {largest_step}");
+        assert!((level - 0.2).abs() < 1e-4, "settled at {level}");
+    }
+
+    #[test]
+    fn seeded_voices_render_identical_noise() {
+        assert_eq!(render_noise(3), render_noise(3));
+        assert_ne!(render_noise(3), render_noise(4));
+    }
+
+    #[test]
+    fn linear_fm_follows_the_bessel_functions() {
+        let samples = render(&[SynthParam::FmMode(FmMode::Linear), SynthParam::FmIndex(1.0)]);
+        // J0(1), J1(1) and J2(1).
+        assert_sidebands(&samples, &[(0, 0.7652), (-1, 0.4401), (1, 0.4401), (-2, 0.1149), (2, 0.1149)]);
+    }
+
+    #[test]
+    fn through_zero_fm_folds_negative_sidebands() {
+        // The deviation of 1280 Hz runs the carrier backwards.
+        let samples = render(&[SynthParam::FmMode(FmMode::ThroughZero), SynthParam::FmIndex(4.0)]);
+        // J0(4) to J5(4), the sidebands from n = -3 folding back above 0 Hz.
+        assert_sidebands(&samples, &[
+            (0, 0.3971),
+            (1, 0.0660),
+            (-1, 0.0660),
+            (2, 0.3641),
+            (-2, 0.3641),
+            (3, 0.4302),
+            (-3, 0.4302),
+            (-4, 0.2811),
+            (-5, 0.1321),
+        ]);
+    }
+
+    #[test]
+    fn ring_mod_leaves_sum_and_difference() {
+        let samples = render(&[SynthParam::RingMod(1.0)]);
+        assert_sidebands(&samples, &[(0, 0.0), (-1, 0.5), (1, 0.5)]);
+        assert!(amplitude_at(&samples, MODULATOR, SAMPLE_RATE) < 0.01);
+    }
+}
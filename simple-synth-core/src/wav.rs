@@ -0,0 +1,311 @@
+use std::{fmt, fs, io, path::Path};
+
+const FORMAT_PCM: u16 = 0x0001;
+const FORMAT_FLOAT: u16 = 0x0003;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Errors produced while reading a WAV file.
+#[derive(Debug)]
+pub enum WavError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file does not start with a `RIFF`/`WAVE` header.
+    NotWave,
+    /// A chunk header or body extends past the end of the file.
+    Truncated,
+    /// The `fmt ` chunk is missing or appears after the `data` chunk.
+    MissingFormat,
+    /// The `data` chunk is missing.
+    MissingData,
+    /// The sample encoding is not 16/24/32-bit PCM or 32/64-bit float.
+    UnsupportedFormat { format_tag: u16, bits_per_sample: u16 },
+    /// The channel count or block alignment is invalid.
+    InvalidLayout,
+    /// The file contains no samples.
+    Empty,
+}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavError::Io(error) => write!(f, "failed to read WAV file: {error}"),
+            WavError::NotWave => write!(f, "not a RIFF/WAVE file"),
+            WavError::Truncated => write!(f, "WAV file is truncated"),
+            WavError::MissingFormat => write!(f, "WAV file has no format chunk before its data"),
+            WavError::MissingData => write!(f, "WAV file has no data chunk"),
+            WavError::UnsupportedFormat { format_tag, bits_per_sample } => write!(
+                f,
+                "unsupported WAV encoding (format {format_tag:#06x}, {bits_per_sample} bits)",
+            ),
+            WavError::InvalidLayout => write!(f, "invalid WAV channel layout"),
+            WavError::Empty => write!(f, "WAV file contains no samples"),
+        }
+    }
+}
+
+impl std::error::Error for WavError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WavError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WavError {
+    fn from(error: io::Error) -> Self {
+        WavError::Io(error)
+    }
+}
+
+/// Decoded contents of a WAV file, with samples converted to `f32` in `[-1.0, 1.0]`.
+#[derive(Debug, Clone)]
+pub struct WavFile {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Interleaved samples.
+    pub samples: Vec<f32>,
+    /// Frame size announced by a Serum-style `clm ` chunk, if present.
+    pub wavetable_frame_size: Option<usize>,
+    /// MIDI note played back at the recorded pitch, from a `smpl` chunk.
+    pub root_key: Option<u8>,
+    /// First loop of a `smpl` chunk as start and end frames, the end being exclusive.
+    pub loop_points: Option<(usize, usize)>,
+}
+
+impl WavFile {
+    /// Reads and decodes a WAV file from disk.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, WavError> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    /// Decodes a WAV file held in memory.
+    pub fn parse(bytes: &[u8]) -> Result<Self, WavError> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(WavError::NotWave);
+        }
+
+        let mut format = None;
+        let mut samples = None;
+        let mut wavetable_frame_size = None;
+        let mut sampler = None;
+        let mut offset = 12;
+
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let size = read_u32(bytes, offset + 4) as usize;
+            let body = bytes
+                .get(offset + 8..)
+                .and_then(|rest| rest.get(..size))
+                .ok_or(WavError::Truncated)?;
+
+            match id {
+                b"fmt " => format = Some(Format::parse(body)?),
+                b"data" => {
+                    let format = format.as_ref().ok_or(WavError::MissingFormat)?;
+                    samples = Some(format.decode(body));
+                },
+                b"clm " => wavetable_frame_size = parse_clm(body),
+                b"smpl" => sampler = parse_smpl(body),
+                _ => (),
+            }
+
+            // Chunks are padded to an even size.
+            offset += 8 + size + (size & 1);
+        }
+
+        let format = format.ok_or(WavError::MissingFormat)?;
+        let samples = samples.ok_or(WavError::MissingData)?;
+        if samples.is_empty() {
+            return Err(WavError::Empty);
+        }
+
+        Ok(Self {
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            samples,
+            wavetable_frame_size,
+            root_key: sampler.map(|sampler| sampler.0),
+            loop_points: sampler.and_then(|sampler| sampler.1),
+        })
+    }
+
+    /// Returns the samples mixed down to a single channel.
+    pub fn to_mono(&self) -> Vec<f32> {
+        let channels = self.channels as usize;
+        if channels == 1 {
+            return self.samples.clone();
+        }
+
+        self.samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    }
+}
+
+struct Format {
+    channels: u16,
+    sample_rate: u32,
+    encoding: Encoding,
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Pcm16,
+    Pcm24,
+    Pcm32,
+    Float32,
+    Float64,
+}
+
+impl Format {
+    fn parse(body: &[u8]) -> Result<Self, WavError> {
+        if body.len() < 16 {
+            return Err(WavError::Truncated);
+        }
+
+        let mut format_tag = read_u16(body, 0);
+        let channels = read_u16(body, 2);
+        let sample_rate = read_u32(body, 4);
+        let block_align = read_u16(body, 12);
+        let bits_per_sample = read_u16(body, 14);
+
+        if format_tag == FORMAT_EXTENSIBLE {
+            // The sub-format GUID starts with the actual format tag.
+            if body.len() < 26 {
+                return Err(WavError::Truncated);
+            }
+            format_tag = read_u16(body, 24);
+        }
+
+        let encoding = match (format_tag, bits_per_sample) {
+            (FORMAT_PCM, 16) => Encoding::Pcm16,
+            (FORMAT_PCM, 24) => Encoding::Pcm24,
+            (FORMAT_PCM, 32) => Encoding::Pcm32,
+            (FORMAT_FLOAT, 32) => Encoding::Float32,
+            (FORMAT_FLOAT, 64) => Encoding::Float64,
+            _ => return Err(WavError::UnsupportedFormat { format_tag, bits_per_sample }),
+        };
+
+        // Widened so a crafted channel count cannot overflow.
+        if channels == 0 || block_align as u32 != channels as u32 * (bits_per_sample as u32 / 8) {
+            return Err(WavError::InvalidLayout);
+        }
+
+        Ok(Self {
+            channels,
+            sample_rate,
+            encoding,
+        })
+    }
+
+    fn decode(&self, body: &[u8]) -> Vec<f32> {
+        match self.encoding {
+            Encoding::Pcm16 => body
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                .collect(),
+            Encoding::Pcm24 => body
+                .chunks_exact(3)
+                .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0)
+                .collect(),
+            Encoding::Pcm32 => body
+                .chunks_exact(4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0)
+                .collect(),
+            Encoding::Float32 => body
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            Encoding::Float64 => body
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32)
+                .collect(),
+        }
+    }
+}
+
+/// Parses the frame size from a Serum `clm ` chunk, e.g. `<!>2048 01000000 wavetable (...)`.
+fn parse_clm(body: &[u8]) -> Option<usize> {
+    let text = std::str::from_utf8(body).ok()?;
+    let digits: String = text
+        .strip_prefix("<!>")?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+
+    digits.parse().ok()
+}
+
+/// Parses the root key and first loop from a `smpl` chunk.
+fn parse_smpl(body: &[u8]) -> Option<(u8, Option<(usize, usize)>)> {
+    if body.len() < 36 {
+        return None;
+    }
+
+    let root_key = read_u32(body, 12).min(127) as u8;
+    // Loops follow the 36-byte header, 24 bytes each, with an inclusive end.
+    let loop_points = (read_u32(body, 28) > 0 && body.len() >= 60)
+        .then(|| (read_u32(body, 44) as usize, read_u32(body, 48) as usize + 1));
+
+    Some((root_key, loop_points))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Result<WavFile, WavError> {
+        WavFile::open(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name))
+    }
+
+    #[test]
+    fn decodes_every_encoding() {
+        for name in ["pcm16.wav", "pcm24.wav", "pcm32.wav", "float32.wav", "float64.wav", "extensible.wav"] {
+            let wav = fixture(name).unwrap();
+            assert_eq!(wav.sample_rate, 48000, "{name}");
+            assert_eq!(wav.channels, 1, "{name}");
+            assert_eq!(wav.samples, [0.0, 0.5, -0.5, -1.0], "{name}");
+        }
+    }
+
+    #[test]
+    fn mixes_channels_down() {
+        let wav = fixture("stereo.wav").unwrap();
+        assert_eq!(wav.channels, 2);
+        assert_eq!(wav.to_mono(), [0.25, -0.75]);
+    }
+
+    #[test]
+    fn reads_the_clm_frame_size_past_padded_chunks() {
+        let wav = fixture("clm.wav").unwrap();
+        assert_eq!(wav.wavetable_frame_size, Some(256));
+        assert_eq!(wav.samples.len(), 512);
+    }
+
+    #[test]
+    fn reports_each_error() {
+        assert!(matches!(fixture("missing.wav"), Err(WavError::Io(_))));
+        assert!(matches!(fixture("not_wave.wav"), Err(WavError::NotWave)));
+        assert!(matches!(fixture("truncated.wav"), Err(WavError::Truncated)));
+        assert!(matches!(fixture("truncated_format.wav"), Err(WavError::Truncated)));
+        assert!(matches!(fixture("missing_format.wav"), Err(WavError::MissingFormat)));
+        assert!(matches!(fixture("missing_data.wav"), Err(WavError::MissingData)));
+        assert!(matches!(
+            fixture("unsupported.wav"),
+            Err(WavError::UnsupportedFormat { format_tag: FORMAT_PCM, bits_per_sample: 8 }),
+        ));
+        assert!(matches!(fixture("invalid_layout.wav"), Err(WavError::InvalidLayout)));
+        assert!(matches!(fixture("layout_overflow.wav"), Err(WavError::InvalidLayout)));
+        assert!(matches!(fixture("empty.wav"), Err(WavError::Empty)));
+    }
+}
@@ -1,24 +1,36 @@
 use std::sync::Arc;
-use nih_plug::prelude::{Editor, GuiContext};
+use nih_plug::prelude::{AsyncExecutor, Editor, GuiContext};
 use nih_plug_iced::widgets as nih_widgets;
 use nih_plug_iced::*;
-use crate::SimpleSynthParams;
+use crate::{PluginTask, SimpleSynth, SimpleSynthParams};
 
 pub(crate) fn default_state() -> Arc<IcedState> {
-    IcedState::from_size(800, 640)
+    IcedState::from_size(800, 760)
 }
 
 pub(crate) fn create(
     params: Arc<SimpleSynthParams>,
     editor_state: Arc<IcedState>,
+    async_executor: AsyncExecutor<SimpleSynth>,
 ) -> Option<Box<dyn Editor>> {
-    create_iced_editor::<SimpleSynthEditor>(editor_state, params)
+    create_iced_editor::<SimpleSynthEditor>(editor_state, (params, async_executor))
 }
 
 struct SimpleSynthEditor {
     params: Arc<SimpleSynthParams>,
     context: Arc<dyn GuiContext>,
+    async_executor: AsyncExecutor<SimpleSynth>,
+    /// Path typed in the sample field, loaded on pressing the load button.
+    sample_path: String,
+    sample_path_state: text_input::State,
+    load_sample_state: button::State,
     gain_slider_state: nih_widgets::param_slider::State,
+    waveform_slider_state: nih_widgets::param_slider::State,
+    osc_mix_slider_state: nih_widgets::param_slider::State,
+    osc2_waveform_slider_state: nih_widgets::param_slider::State,
+    osc2_octave_slider_state: nih_widgets::param_slider::State,
+    osc2_semitone_slider_state: nih_widgets::param_slider::State,
+    osc2_fine_slider_state: nih_widgets::param_slider::State,
     attack_slider_state: nih_widgets::param_slider::State,
     decay_slider_state: nih_widgets::param_slider::State,
     sustain_slider_state: nih_widgets::param_slider::State,
@@ -30,26 +42,43 @@ struct SimpleSynthEditor {
     filter_decay_slider_state: nih_widgets::param_slider::State,
     filter_sustain_slider_state: nih_widgets::param_slider::State,
     filter_release_slider_state: nih_widgets::param_slider::State,
+    sample_root_key_slider_state: nih_widgets::param_slider::State,
+    sample_loop_mode_slider_state: nih_widgets::param_slider::State,
+    sample_loop_start_slider_state: nih_widgets::param_slider::State,
+    sample_loop_end_slider_state: nih_widgets::param_slider::State,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum Message {
     ParamUpdate(nih_widgets::ParamMessage),
+    SamplePathChanged(String),
+    LoadSample,
 }
 
 impl IcedEditor for SimpleSynthEditor {
     type Executor = executor::Default;
     type Message = Message;
-    type InitializationFlags = Arc<SimpleSynthParams>;
+    type InitializationFlags = (Arc<SimpleSynthParams>, AsyncExecutor<SimpleSynth>);
 
     fn new(
-        params: Self::InitializationFlags,
+        (params, async_executor): Self::InitializationFlags,
         context: Arc<dyn GuiContext>,
     ) -> (Self, Command<Self::Message>) {
+        let sample_path = params.sample_path.read().map(|path| path.clone()).unwrap_or_default();
         let editor = SimpleSynthEditor {
             params,
             context,
+            async_executor,
+            sample_path,
+            sample_path_state: Default::default(),
+            load_sample_state: Default::default(),
             gain_slider_state: Default::default(),
+            waveform_slider_state: Default::default(),
+            osc_mix_slider_state: Default::default(),
+            osc2_waveform_slider_state: Default::default(),
+            osc2_octave_slider_state: Default::default(),
+            osc2_semitone_slider_state: Default::default(),
+            osc2_fine_slider_state: Default::default(),
             attack_slider_state: Default::default(),
             decay_slider_state: Default::default(),
             sustain_slider_state: Default::default(),
@@ -61,6 +90,10 @@ impl IcedEditor for SimpleSynthEditor {
             filter_decay_slider_state: Default::default(),
             filter_sustain_slider_state: Default::default(),
             filter_release_slider_state: Default::default(),
+            sample_root_key_slider_state: Default::default(),
+            sample_loop_mode_slider_state: Default::default(),
+            sample_loop_start_slider_state: Default::default(),
+            sample_loop_end_slider_state: Default::default(),
         };
 
         (editor, Command::none())
@@ -77,12 +110,29 @@ impl IcedEditor for SimpleSynthEditor {
     ) -> Command<Self::Message> {
         match message {
             Message::ParamUpdate(message) => self.handle_param_message(message),
+            Message::SamplePathChanged(path) => self.sample_path = path,
+            Message::LoadSample => {
+                self.async_executor.execute_background(PluginTask::LoadSample(self.sample_path.clone()));
+            },
         }
 
         Command::none()
     }
 
     fn view(&mut self) -> Element<'_, Self::Message> {
+        let waveform_slider = nih_widgets::ParamSlider::new(&mut self.waveform_slider_state, &self.params.waveform)
+            .map(Message::ParamUpdate);
+        let osc_mix_slider = nih_widgets::ParamSlider::new(&mut self.osc_mix_slider_state, &self.params.osc_mix)
+            .map(Message::ParamUpdate);
+        let osc2_waveform_slider = nih_widgets::ParamSlider::new(&mut self.osc2_waveform_slider_state, &self.params.osc2_waveform)
+            .map(Message::ParamUpdate);
+        let osc2_octave_slider = nih_widgets::ParamSlider::new(&mut self.osc2_octave_slider_state, &self.params.osc2_octave)
+            .map(Message::ParamUpdate);
+        let osc2_semitone_slider = nih_widgets::ParamSlider::new(&mut self.osc2_semitone_slider_state, &self.params.osc2_semitone)
+            .map(Message::ParamUpdate);
+        let osc2_fine_slider = nih_widgets::ParamSlider::new(&mut self.osc2_fine_slider_state, &self.params.osc2_fine)
+            .map(Message::ParamUpdate);
+
         let attack_slider = nih_widgets::ParamSlider::new(&mut self.attack_slider_state, &self.params.attack)
             .map(Message::ParamUpdate);
         let decay_slider = nih_widgets::ParamSlider::new(&mut self.decay_slider_state, &self.params.decay)
@@ -107,6 +157,94 @@ impl IcedEditor for SimpleSynthEditor {
         let filter_release_slider = nih_widgets::ParamSlider::new(&mut self.filter_release_slider_state, &self.params.filter_release)
             .map(Message::ParamUpdate);
 
+        let sample_path_input = TextInput::new(
+            &mut self.sample_path_state,
+            "Path to a WAV file",
+            &self.sample_path,
+            Message::SamplePathChanged,
+        )
+        .on_submit(Message::LoadSample)
+        .padding(5)
+        .width(300.into());
+        let load_sample_button = Button::new(&mut self.load_sample_state, Text::new("Load"))
+            .on_press(Message::LoadSample);
+        let sample_root_key_slider = nih_widgets::ParamSlider::new(&mut self.sample_root_key_slider_state, &self.params.sample_root_key)
+            .map(Message::ParamUpdate);
+        let sample_loop_mode_slider = nih_widgets::ParamSlider::new(&mut self.sample_loop_mode_slider_state, &self.params.sample_loop_mode)
+            .map(Message::ParamUpdate);
+        let sample_loop_start_slider = nih_widgets::ParamSlider::new(&mut self.sample_loop_start_slider_state, &self.params.sample_loop_start)
+            .map(Message::ParamUpdate);
+        let sample_loop_end_slider = nih_widgets::ParamSlider::new(&mut self.sample_loop_end_slider_state, &self.params.sample_loop_end)
+            .map(Message::ParamUpdate);
+
+        let osc_row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Waveform"))
+                .push(waveform_slider)
+            )
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Osc Mix"))
+                .push(osc_mix_slider)
+            );
+
+        let osc2_row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Osc 2 Waveform"))
+                .push(osc2_waveform_slider)
+            )
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Octave"))
+                .push(osc2_octave_slider)
+            )
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Semitone"))
+                .push(osc2_semitone_slider)
+            )
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Fine"))
+                .push(osc2_fine_slider)
+            );
+
+        let sample_row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(sample_path_input)
+            .push(load_sample_button);
+
+        let sample_params_row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Root Key"))
+                .push(sample_root_key_slider)
+            )
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Loop Mode"))
+                .push(sample_loop_mode_slider)
+            )
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Loop Start"))
+                .push(sample_loop_start_slider)
+            )
+            .push(Column::new()
+                .align_items(Alignment::Center)
+                .push(Text::new("Loop End"))
+                .push(sample_loop_end_slider)
+            );
+
         let env_row = Row::new()
             .spacing(10)
             .align_items(Alignment::Center)
@@ -200,11 +338,14 @@ impl IcedEditor for SimpleSynthEditor {
                 nih_widgets::ParamSlider::new(&mut self.gain_slider_state, &self.params.gain)
                     .map(Message::ParamUpdate),
             )
-            // .push(Space::with_height(10.into()))
-            // .push(Column::new()
-            //     .push(Text::new("Waveform"))
-            //     .push(waveform_dropdown)
-            // )
+            .push(Space::with_height(10.into()))
+            .push(osc_row)
+            .push(Space::with_height(10.into()))
+            .push(osc2_row)
+            .push(Space::with_height(10.into()))
+            .push(sample_row)
+            .push(Space::with_height(10.into()))
+            .push(sample_params_row)
             .push(Space::with_height(10.into()))
             .push(env_row)
             .push(Space::with_height(10.into()))
@@ -1,14 +1,31 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
 use nih_plug::{prelude::*, util::db_to_gain};
 use nih_plug_iced::IcedState;
 
 mod editor;
 
 use simple_synth_core::{
+    Breakpoint,
     EnvelopeStage,
+    EnvelopeType,
     FilterMode,
+    FmMode,
+    Interpolation,
+    LoopMode,
+    MAX_PARTIALS,
+    MAX_UNISON_VOICES,
+    NoiseColor,
+    OscillatorQuality,
+    OscillatorType,
+    PhaseMode,
+    PluckExcitation,
+    RetriggerMode,
+    Sample,
+    ShapeMode,
+    SubWaveform,
     Waveform,
     SynthParam,
+    VelocityCurve,
     VoiceManager,
 };
 
@@ -24,16 +41,232 @@ pub enum WaveformParam {
     Sawtooth,
     #[name = "Noise"]
     Noise,
+    #[name = "Wavetable"]
+    Wavetable,
+    #[name = "Pluck"]
+    Pluck,
+    #[name = "Additive"]
+    Additive,
+    #[name = "Sample"]
+    Sample,
 }
 
-impl From<WaveformParam> for Waveform {
+impl WaveformParam {
+    /// Returns the basic waveform, or `None` for choices served by another oscillator type.
+    fn waveform(self) -> Option<Waveform> {
+        match self {
+            WaveformParam::Sine => Some(Waveform::Sine),
+            WaveformParam::Square => Some(Waveform::Square),
+            WaveformParam::Triangle => Some(Waveform::Triangle),
+            WaveformParam::Sawtooth => Some(Waveform::Sawtooth),
+            WaveformParam::Noise => Some(Waveform::Noise),
+            WaveformParam::Wavetable | WaveformParam::Pluck | WaveformParam::Additive | WaveformParam::Sample => None,
+        }
+    }
+}
+
+impl From<WaveformParam> for OscillatorType {
     fn from(param: WaveformParam) -> Self {
         match param {
-            WaveformParam::Sine => Waveform::Sine,
-            WaveformParam::Square => Waveform::Square,
-            WaveformParam::Triangle => Waveform::Triangle,
-            WaveformParam::Sawtooth => Waveform::Sawtooth,
-            WaveformParam::Noise => Waveform::Noise,
+            WaveformParam::Wavetable => OscillatorType::Wavetable,
+            WaveformParam::Pluck => OscillatorType::Pluck,
+            WaveformParam::Additive => OscillatorType::Additive,
+            WaveformParam::Sample => OscillatorType::Sample,
+            _ => OscillatorType::Basic,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum OscillatorQualityParam {
+    #[name = "Naive"]
+    Naive,
+    #[name = "Band-limited"]
+    BandLimited,
+}
+
+impl From<OscillatorQualityParam> for OscillatorQuality {
+    fn from(param: OscillatorQualityParam) -> Self {
+        match param {
+            OscillatorQualityParam::Naive => OscillatorQuality::Naive,
+            OscillatorQualityParam::BandLimited => OscillatorQuality::BandLimited,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum NoiseColorParam {
+    #[name = "White"]
+    White,
+    #[name = "Pink"]
+    Pink,
+    #[name = "Brown"]
+    Brown,
+    #[name = "Blue"]
+    Blue,
+}
+
+impl From<NoiseColorParam> for NoiseColor {
+    fn from(param: NoiseColorParam) -> Self {
+        match param {
+            NoiseColorParam::White => NoiseColor::White,
+            NoiseColorParam::Pink => NoiseColor::Pink,
+            NoiseColorParam::Brown => NoiseColor::Brown,
+            NoiseColorParam::Blue => NoiseColor::Blue,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum PluckExcitationParam {
+    #[name = "Noise Burst"]
+    Noise,
+    #[name = "Filtered Noise"]
+    FilteredNoise,
+    #[name = "Impulse"]
+    Impulse,
+}
+
+impl From<PluckExcitationParam> for PluckExcitation {
+    fn from(param: PluckExcitationParam) -> Self {
+        match param {
+            PluckExcitationParam::Noise => PluckExcitation::Noise,
+            PluckExcitationParam::FilteredNoise => PluckExcitation::FilteredNoise,
+            PluckExcitationParam::Impulse => PluckExcitation::Impulse,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum InterpolationParam {
+    #[name = "Linear"]
+    Linear,
+    #[name = "Hermite"]
+    Hermite,
+}
+
+impl From<InterpolationParam> for Interpolation {
+    fn from(param: InterpolationParam) -> Self {
+        match param {
+            InterpolationParam::Linear => Interpolation::Linear,
+            InterpolationParam::Hermite => Interpolation::Hermite,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum LoopModeParam {
+    #[name = "Off"]
+    Off,
+    #[name = "Forward"]
+    Forward,
+    #[name = "Ping-Pong"]
+    PingPong,
+}
+
+impl From<LoopModeParam> for LoopMode {
+    fn from(param: LoopModeParam) -> Self {
+        match param {
+            LoopModeParam::Off => LoopMode::Off,
+            LoopModeParam::Forward => LoopMode::Forward,
+            LoopModeParam::PingPong => LoopMode::PingPong,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum SubWaveformParam {
+    #[name = "Square"]
+    Square,
+    #[name = "Sine"]
+    Sine,
+}
+
+impl From<SubWaveformParam> for SubWaveform {
+    fn from(param: SubWaveformParam) -> Self {
+        match param {
+            SubWaveformParam::Square => SubWaveform::Square,
+            SubWaveformParam::Sine => SubWaveform::Sine,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum FmModeParam {
+    #[name = "Off"]
+    Off,
+    #[name = "Linear"]
+    Linear,
+    #[name = "Through-zero"]
+    ThroughZero,
+}
+
+impl From<FmModeParam> for FmMode {
+    fn from(param: FmModeParam) -> Self {
+        match param {
+            FmModeParam::Off => FmMode::Off,
+            FmModeParam::Linear => FmMode::Linear,
+            FmModeParam::ThroughZero => FmMode::ThroughZero,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum ShapeModeParam {
+    #[name = "Off"]
+    Off,
+    #[name = "Phase Distortion"]
+    PhaseDistortion,
+    #[name = "Wavefolder"]
+    Wavefolder,
+    #[name = "Waveshaper"]
+    Waveshaper,
+}
+
+impl From<ShapeModeParam> for ShapeMode {
+    fn from(param: ShapeModeParam) -> Self {
+        match param {
+            ShapeModeParam::Off => ShapeMode::Off,
+            ShapeModeParam::PhaseDistortion => ShapeMode::PhaseDistortion,
+            ShapeModeParam::Wavefolder => ShapeMode::Wavefolder,
+            ShapeModeParam::Waveshaper => ShapeMode::Waveshaper,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum PhaseModeParam {
+    #[name = "Free Running"]
+    FreeRunning,
+    #[name = "Reset"]
+    Reset,
+}
+
+impl From<PhaseModeParam> for PhaseMode {
+    fn from(param: PhaseModeParam) -> Self {
+        match param {
+            PhaseModeParam::FreeRunning => PhaseMode::FreeRunning,
+            PhaseModeParam::Reset => PhaseMode::Reset,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum RetriggerModeParam {
+    #[name = "Reset"]
+    Reset,
+    #[name = "Continue"]
+    Continue,
+    #[name = "Legato"]
+    Legato,
+}
+
+impl From<RetriggerModeParam> for RetriggerMode {
+    fn from(param: RetriggerModeParam) -> Self {
+        match param {
+            RetriggerModeParam::Reset => RetriggerMode::Reset,
+            RetriggerModeParam::Continue => RetriggerMode::Continue,
+            RetriggerModeParam::Legato => RetriggerMode::Legato,
         }
     }
 }
@@ -46,6 +279,22 @@ pub enum FilterModeParam {
     Highpass,
     #[name = "Bandpass"]
     Bandpass,
+    #[name = "Biquad Lowpass"]
+    BiquadLowpass,
+    #[name = "Biquad Highpass"]
+    BiquadHighpass,
+    #[name = "Biquad Bandpass"]
+    BiquadBandpass,
+    #[name = "Notch"]
+    Notch,
+    #[name = "Allpass"]
+    Allpass,
+    #[name = "Peak"]
+    Peak,
+    #[name = "Low Shelf"]
+    LowShelf,
+    #[name = "High Shelf"]
+    HighShelf,
 }
 
 impl From<FilterModeParam> for FilterMode {
@@ -54,47 +303,403 @@ impl From<FilterModeParam> for FilterMode {
             FilterModeParam::Lowpass => FilterMode::Lowpass,
             FilterModeParam::Highpass => FilterMode::Highpass,
             FilterModeParam::Bandpass => FilterMode::Bandpass,
+            FilterModeParam::BiquadLowpass => FilterMode::BiquadLowpass,
+            FilterModeParam::BiquadHighpass => FilterMode::BiquadHighpass,
+            FilterModeParam::BiquadBandpass => FilterMode::BiquadBandpass,
+            FilterModeParam::Notch => FilterMode::Notch,
+            FilterModeParam::Allpass => FilterMode::Allpass,
+            FilterModeParam::Peak => FilterMode::Peak,
+            FilterModeParam::LowShelf => FilterMode::LowShelf,
+            FilterModeParam::HighShelf => FilterMode::HighShelf,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum FilterEnvTypeParam {
+    #[name = "ADSR"]
+    Adsr,
+    #[name = "Breakpoint"]
+    Breakpoint,
+}
+
+impl From<FilterEnvTypeParam> for EnvelopeType {
+    fn from(param: FilterEnvTypeParam) -> Self {
+        match param {
+            FilterEnvTypeParam::Adsr => EnvelopeType::Adsr,
+            FilterEnvTypeParam::Breakpoint => EnvelopeType::Breakpoint,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum VelocityCurveParam {
+    #[name = "Linear"]
+    Linear,
+    #[name = "Soft"]
+    Soft,
+    #[name = "Hard"]
+    Hard,
+}
+
+impl From<VelocityCurveParam> for VelocityCurve {
+    fn from(param: VelocityCurveParam) -> Self {
+        match param {
+            VelocityCurveParam::Linear => VelocityCurve::Linear,
+            VelocityCurveParam::Soft => VelocityCurve::Soft,
+            VelocityCurveParam::Hard => VelocityCurve::Hard,
+        }
+    }
+}
+
+/// Note length of a tempo-synced time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum NoteDivisionParam {
+    #[name = "1/1"]
+    Whole,
+    #[name = "1/2 D"]
+    HalfDotted,
+    #[name = "1/2"]
+    Half,
+    #[name = "1/2 T"]
+    HalfTriplet,
+    #[name = "1/4 D"]
+    QuarterDotted,
+    #[name = "1/4"]
+    Quarter,
+    #[name = "1/4 T"]
+    QuarterTriplet,
+    #[name = "1/8 D"]
+    EighthDotted,
+    #[name = "1/8"]
+    Eighth,
+    #[name = "1/8 T"]
+    EighthTriplet,
+    #[name = "1/16 D"]
+    SixteenthDotted,
+    #[name = "1/16"]
+    Sixteenth,
+    #[name = "1/16 T"]
+    SixteenthTriplet,
+    #[name = "1/32"]
+    ThirtySecond,
+}
+
+impl NoteDivisionParam {
+    /// Length in quarter notes.
+    fn beats(self) -> f32 {
+        match self {
+            NoteDivisionParam::Whole => 4.0,
+            NoteDivisionParam::HalfDotted => 3.0,
+            NoteDivisionParam::Half => 2.0,
+            NoteDivisionParam::HalfTriplet => 4.0 / 3.0,
+            NoteDivisionParam::QuarterDotted => 1.5,
+            NoteDivisionParam::Quarter => 1.0,
+            NoteDivisionParam::QuarterTriplet => 2.0 / 3.0,
+            NoteDivisionParam::EighthDotted => 0.75,
+            NoteDivisionParam::Eighth => 0.5,
+            NoteDivisionParam::EighthTriplet => 1.0 / 3.0,
+            NoteDivisionParam::SixteenthDotted => 0.375,
+            NoteDivisionParam::Sixteenth => 0.25,
+            NoteDivisionParam::SixteenthTriplet => 1.0 / 6.0,
+            NoteDivisionParam::ThirtySecond => 0.125,
+        }
+    }
+
+    /// Length in seconds at the tempo in beats per minute.
+    fn seconds(self, tempo: f32) -> f32 {
+        self.beats() * 60.0 / tempo
+    }
+}
+
+/// Breakpoints of the breakpoint envelope editable in the plugin.
+const BREAKPOINT_PARAMS: usize = 8;
+
+/// One breakpoint of the breakpoint envelope, see [`simple_synth_core::Breakpoint`].
+#[derive(Params)]
+struct BreakpointParams {
+    /// Time in milliseconds from the previous breakpoint, never synced to the host tempo unlike
+    /// the DAHDSR envelope times.
+    #[id = "time"]
+    time: FloatParam,
+    #[id = "level"]
+    level: FloatParam,
+    #[id = "curve"]
+    curve: FloatParam,
+}
+
+impl BreakpointParams {
+    fn new(time: f32, level: f32, curve: f32) -> Self {
+        Self {
+            time: FloatParam::new(
+                "Time",
+                time,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 30000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                }
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            level: FloatParam::new(
+                "Level",
+                level,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            curve: FloatParam::new(
+                "Curve",
+                curve,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
         }
     }
 }
 
+/// Work run off the audio thread.
+enum PluginTask {
+    /// Loads the WAV file at the path for the sample oscillator.
+    LoadSample(String),
+}
+
+/// Sample handed over from the background loader to the audio thread.
+#[derive(Default)]
+struct SampleSlot {
+    /// Loaded sample waiting for the audio thread.
+    pending: Option<Arc<Sample>>,
+    /// Sample replaced on the audio thread, freed by the next load rather than during processing.
+    retired: Option<Arc<Sample>>,
+}
+
 struct SimpleSynth {
     params: Arc<SimpleSynthParams>,
     voice_manager: VoiceManager,
+    /// Sample shared with the voices.
+    sample: Option<Arc<Sample>>,
+    sample_slot: Arc<Mutex<SampleSlot>>,
 }
 
 #[derive(Params)]
 struct SimpleSynthParams {
     #[persist = "editor-state"]
     editor_state: Arc<IcedState>,
+    /// Path of the WAV file played by the sample oscillator, empty when none is loaded.
+    #[persist = "sample-path"]
+    sample_path: Arc<RwLock<String>>,
     #[id = "gain"]
     gain: FloatParam,
     #[id = "waveform"]
     waveform: EnumParam<WaveformParam>,
+    #[id = "quality"]
+    quality: EnumParam<OscillatorQualityParam>,
+    #[id = "noise_color"]
+    noise_color: EnumParam<NoiseColorParam>,
+    #[id = "wavetable_position"]
+    wavetable_position: FloatParam,
+    #[id = "pluck_excitation"]
+    pluck_excitation: EnumParam<PluckExcitationParam>,
+    #[id = "pluck_damping"]
+    pluck_damping: FloatParam,
+    #[id = "pluck_brightness"]
+    pluck_brightness: FloatParam,
+    #[id = "pluck_decay"]
+    pluck_decay: FloatParam,
+    #[id = "additive_partials"]
+    additive_partials: IntParam,
+    #[id = "additive_brightness"]
+    additive_brightness: FloatParam,
+    #[id = "additive_odd_even"]
+    additive_odd_even: FloatParam,
+    #[id = "additive_stretch"]
+    additive_stretch: FloatParam,
+    #[id = "sample_interpolation"]
+    sample_interpolation: EnumParam<InterpolationParam>,
+    #[id = "sample_loop_mode"]
+    sample_loop_mode: EnumParam<LoopModeParam>,
+    #[id = "sample_loop_start"]
+    sample_loop_start: FloatParam,
+    #[id = "sample_loop_end"]
+    sample_loop_end: FloatParam,
+    #[id = "sample_crossfade"]
+    sample_crossfade: FloatParam,
+    #[id = "sample_root_key"]
+    sample_root_key: IntParam,
+    #[id = "sample_fine_tune"]
+    sample_fine_tune: FloatParam,
+    #[id = "osc2_waveform"]
+    osc2_waveform: EnumParam<WaveformParam>,
+    #[id = "osc2_octave"]
+    osc2_octave: IntParam,
+    #[id = "osc2_semitone"]
+    osc2_semitone: IntParam,
+    #[id = "osc2_fine"]
+    osc2_fine: FloatParam,
+    #[id = "osc_mix"]
+    osc_mix: FloatParam,
+    #[id = "sub_waveform"]
+    sub_waveform: EnumParam<SubWaveformParam>,
+    #[id = "sub_octave"]
+    sub_octave: IntParam,
+    #[id = "sub_level"]
+    sub_level: FloatParam,
+    #[id = "osc_sync"]
+    osc_sync: BoolParam,
+    #[id = "sync_pitch"]
+    sync_pitch: FloatParam,
+    #[id = "sync_env_amount"]
+    sync_env_amount: FloatParam,
+    #[id = "fm_mode"]
+    fm_mode: EnumParam<FmModeParam>,
+    #[id = "fm_index"]
+    fm_index: FloatParam,
+    #[id = "ring_mod"]
+    ring_mod: FloatParam,
+    #[id = "pulse_width"]
+    pulse_width: FloatParam,
+    #[id = "pwm_depth"]
+    pwm_depth: FloatParam,
+    #[id = "pwm_rate"]
+    pwm_rate: FloatParam,
+    #[id = "pwm_sync"]
+    pwm_sync: BoolParam,
+    #[id = "pwm_division"]
+    pwm_division: EnumParam<NoteDivisionParam>,
+    #[id = "shape_mode"]
+    shape_mode: EnumParam<ShapeModeParam>,
+    #[id = "shape_amount"]
+    shape_amount: FloatParam,
+    #[id = "shape_env_amount"]
+    shape_env_amount: FloatParam,
+    #[id = "drift_amount"]
+    drift_amount: FloatParam,
+    #[id = "drift_rate"]
+    drift_rate: FloatParam,
+    #[id = "random_detune"]
+    random_detune: FloatParam,
+    #[id = "osc1_phase_mode"]
+    osc1_phase_mode: EnumParam<PhaseModeParam>,
+    #[id = "osc2_phase_mode"]
+    osc2_phase_mode: EnumParam<PhaseModeParam>,
+    #[id = "unison_voices"]
+    unison_voices: IntParam,
+    #[id = "unison_detune"]
+    unison_detune: FloatParam,
+    #[id = "unison_detune_curve"]
+    unison_detune_curve: FloatParam,
+    #[id = "unison_random_phase"]
+    unison_random_phase: FloatParam,
+    #[id = "unison_spread"]
+    unison_spread: FloatParam,
+    #[id = "delay"]
+    delay: FloatParam,
     #[id = "attack"]
     attack: FloatParam,
+    #[id = "hold"]
+    hold: FloatParam,
     #[id = "decay"]
     decay: FloatParam,
     #[id = "sustain"]
     sustain: FloatParam,
     #[id = "release"]
     release: FloatParam,
+    #[id = "attack_curve"]
+    attack_curve: FloatParam,
+    #[id = "decay_curve"]
+    decay_curve: FloatParam,
+    #[id = "release_curve"]
+    release_curve: FloatParam,
+    #[id = "env_sync"]
+    env_sync: BoolParam,
+    #[id = "delay_division"]
+    delay_division: EnumParam<NoteDivisionParam>,
+    #[id = "attack_division"]
+    attack_division: EnumParam<NoteDivisionParam>,
+    #[id = "hold_division"]
+    hold_division: EnumParam<NoteDivisionParam>,
+    #[id = "decay_division"]
+    decay_division: EnumParam<NoteDivisionParam>,
+    #[id = "release_division"]
+    release_division: EnumParam<NoteDivisionParam>,
+    #[id = "retrigger_mode"]
+    retrigger_mode: EnumParam<RetriggerModeParam>,
     #[id = "filter_mode"]
     filter_mode: EnumParam<FilterModeParam>,
     #[id = "cutoff"]
     cutoff: FloatParam,
     #[id = "resonance"]
     resonance: FloatParam,
+    #[id = "filter_gain"]
+    filter_gain: FloatParam,
     #[id = "filter_env_amount"]
     filter_env_amount: FloatParam,
+    #[id = "filter_env_type"]
+    filter_env_type: EnumParam<FilterEnvTypeParam>,
+    #[nested(array, group = "Breakpoint")]
+    breakpoints: [BreakpointParams; BREAKPOINT_PARAMS],
+    #[id = "breakpoint_count"]
+    breakpoint_count: IntParam,
+    #[id = "breakpoint_sustain"]
+    breakpoint_sustain: IntParam,
+    #[id = "breakpoint_loop"]
+    breakpoint_loop: BoolParam,
+    #[id = "breakpoint_loop_start"]
+    breakpoint_loop_start: IntParam,
+    #[id = "breakpoint_loop_end"]
+    breakpoint_loop_end: IntParam,
+    #[id = "breakpoint_pitch"]
+    breakpoint_pitch: FloatParam,
+    #[id = "breakpoint_cutoff"]
+    breakpoint_cutoff: FloatParam,
+    #[id = "breakpoint_mix"]
+    breakpoint_mix: FloatParam,
+    #[id = "breakpoint_pulse_width"]
+    breakpoint_pulse_width: FloatParam,
+    #[id = "breakpoint_shape"]
+    breakpoint_shape: FloatParam,
+    #[id = "filter_delay"]
+    filter_delay: FloatParam,
     #[id = "filter_attack"]
     filter_attack: FloatParam,
+    #[id = "filter_hold"]
+    filter_hold: FloatParam,
     #[id = "filter_decay"]
     filter_decay: FloatParam,
     #[id = "filter_sustain"]
     filter_sustain: FloatParam,
     #[id = "filter_release"]
     filter_release: FloatParam,
+    #[id = "filter_attack_curve"]
+    filter_attack_curve: FloatParam,
+    #[id = "filter_decay_curve"]
+    filter_decay_curve: FloatParam,
+    #[id = "filter_release_curve"]
+    filter_release_curve: FloatParam,
+    #[id = "filter_env_sync"]
+    filter_env_sync: BoolParam,
+    #[id = "filter_delay_division"]
+    filter_delay_division: EnumParam<NoteDivisionParam>,
+    #[id = "filter_attack_division"]
+    filter_attack_division: EnumParam<NoteDivisionParam>,
+    #[id = "filter_hold_division"]
+    filter_hold_division: EnumParam<NoteDivisionParam>,
+    #[id = "filter_decay_division"]
+    filter_decay_division: EnumParam<NoteDivisionParam>,
+    #[id = "filter_release_division"]
+    filter_release_division: EnumParam<NoteDivisionParam>,
+    #[id = "velocity_curve"]
+    velocity_curve: EnumParam<VelocityCurveParam>,
+    #[id = "velocity_amp"]
+    velocity_amp: FloatParam,
+    #[id = "velocity_filter_env"]
+    velocity_filter_env: FloatParam,
+    #[id = "velocity_attack"]
+    velocity_attack: FloatParam,
 }
 
 impl Default for SimpleSynth {
@@ -102,6 +707,8 @@ impl Default for SimpleSynth {
         Self {
             params: Arc::new(SimpleSynthParams::default()),
             voice_manager: VoiceManager::new(44100.0),
+            sample: None,
+            sample_slot: Arc::new(Mutex::new(SampleSlot::default())),
         }
     }
 }
@@ -110,6 +717,7 @@ impl Default for SimpleSynthParams {
     fn default() -> Self {
         Self {
             editor_state: editor::default_state(),
+            sample_path: Arc::new(RwLock::new(String::new())),
             gain: FloatParam::new(
                 "Gain",
                 0.0,
@@ -122,6 +730,350 @@ impl Default for SimpleSynthParams {
             .with_smoother(SmoothingStyle::Linear(50.0))
             .with_unit(" dB"),
             waveform: EnumParam::new("Waveform", WaveformParam::Sine),
+            quality: EnumParam::new("Quality", OscillatorQualityParam::BandLimited),
+            noise_color: EnumParam::new("Noise Color", NoiseColorParam::White),
+            wavetable_position: FloatParam::new(
+                "Wavetable Position",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            pluck_excitation: EnumParam::new("Pluck Excitation", PluckExcitationParam::Noise),
+            pluck_damping: FloatParam::new(
+                "Pluck Damping",
+                0.5,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            pluck_brightness: FloatParam::new(
+                "Pluck Brightness",
+                1.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            pluck_decay: FloatParam::new(
+                "Pluck Decay",
+                2.0,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 30.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                }
+            )
+            .with_step_size(0.01)
+            .with_unit(" s"),
+            additive_partials: IntParam::new(
+                "Additive Partials",
+                64,
+                IntRange::Linear {
+                    min: 1,
+                    max: MAX_PARTIALS as i32,
+                }
+            ),
+            additive_brightness: FloatParam::new(
+                "Additive Brightness",
+                0.5,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            additive_odd_even: FloatParam::new(
+                "Additive Odd/Even",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            additive_stretch: FloatParam::new(
+                "Additive Stretch",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            sample_interpolation: EnumParam::new("Sample Interpolation", InterpolationParam::Hermite),
+            sample_loop_mode: EnumParam::new("Sample Loop Mode", LoopModeParam::Off),
+            sample_loop_start: FloatParam::new(
+                "Sample Loop Start",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sample_loop_end: FloatParam::new(
+                "Sample Loop End",
+                1.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sample_crossfade: FloatParam::new(
+                "Sample Crossfade",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 0.5,
+                }
+            ),
+            sample_root_key: IntParam::new(
+                "Sample Root Key",
+                60,
+                IntRange::Linear {
+                    min: 0,
+                    max: 127,
+                }
+            ),
+            sample_fine_tune: FloatParam::new(
+                "Sample Fine Tune",
+                0.0,
+                FloatRange::Linear {
+                    min: -100.0,
+                    max: 100.0,
+                }
+            )
+            .with_step_size(0.1)
+            .with_unit(" ct"),
+            osc2_waveform: EnumParam::new("Osc 2 Waveform", WaveformParam::Sawtooth),
+            osc2_octave: IntParam::new(
+                "Osc 2 Octave",
+                0,
+                IntRange::Linear {
+                    min: -4,
+                    max: 4,
+                }
+            ),
+            osc2_semitone: IntParam::new(
+                "Osc 2 Semitone",
+                0,
+                IntRange::Linear {
+                    min: -12,
+                    max: 12,
+                }
+            )
+            .with_unit(" st"),
+            osc2_fine: FloatParam::new(
+                "Osc 2 Fine",
+                0.0,
+                FloatRange::Linear {
+                    min: -100.0,
+                    max: 100.0,
+                }
+            )
+            .with_step_size(0.1)
+            .with_unit(" ct"),
+            osc_mix: FloatParam::new(
+                "Osc Mix",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            sub_waveform: EnumParam::new("Sub Waveform", SubWaveformParam::Square),
+            sub_octave: IntParam::new(
+                "Sub Octave",
+                -1,
+                IntRange::Linear {
+                    min: -2,
+                    max: -1,
+                }
+            ),
+            sub_level: FloatParam::new(
+                "Sub Level",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            osc_sync: BoolParam::new("Osc Sync", false),
+            sync_pitch: FloatParam::new(
+                "Sync Pitch",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 48.0,
+                }
+            )
+            .with_step_size(0.01)
+            .with_unit(" st"),
+            sync_env_amount: FloatParam::new(
+                "Sync Env Amount",
+                0.0,
+                FloatRange::Linear {
+                    min: -48.0,
+                    max: 48.0,
+                }
+            )
+            .with_step_size(0.01)
+            .with_unit(" st"),
+            fm_mode: EnumParam::new("FM Mode", FmModeParam::Off),
+            fm_index: FloatParam::new(
+                "FM Index",
+                0.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 16.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                }
+            )
+            .with_step_size(0.01),
+            ring_mod: FloatParam::new(
+                "Ring Mod",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            pulse_width: FloatParam::new(
+                "Pulse Width",
+                0.5,
+                FloatRange::Linear {
+                    min: 0.01,
+                    max: 0.99,
+                }
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            pwm_depth: FloatParam::new(
+                "PWM Depth",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 0.49,
+                }
+            ),
+            pwm_rate: FloatParam::new(
+                "PWM Rate",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                }
+            )
+            .with_unit(" Hz"),
+            pwm_sync: BoolParam::new("PWM Sync", false),
+            pwm_division: EnumParam::new("PWM Division", NoteDivisionParam::Quarter),
+            shape_mode: EnumParam::new("Shape Mode", ShapeModeParam::Off),
+            shape_amount: FloatParam::new(
+                "Shape Amount",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            shape_env_amount: FloatParam::new(
+                "Shape Env Amount",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            drift_amount: FloatParam::new(
+                "Drift Amount",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 50.0,
+                }
+            )
+            .with_step_size(0.1)
+            .with_unit(" ct"),
+            drift_rate: FloatParam::new(
+                "Drift Rate",
+                0.5,
+                FloatRange::Skewed {
+                    min: 0.05,
+                    max: 5.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                }
+            )
+            .with_unit(" Hz"),
+            random_detune: FloatParam::new(
+                "Random Detune",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 50.0,
+                }
+            )
+            .with_step_size(0.1)
+            .with_unit(" ct"),
+            osc1_phase_mode: EnumParam::new("Osc 1 Phase Mode", PhaseModeParam::FreeRunning),
+            osc2_phase_mode: EnumParam::new("Osc 2 Phase Mode", PhaseModeParam::FreeRunning),
+            unison_voices: IntParam::new(
+                "Unison Voices",
+                1,
+                IntRange::Linear {
+                    min: 1,
+                    max: MAX_UNISON_VOICES as i32,
+                }
+            ),
+            unison_detune: FloatParam::new(
+                "Unison Detune",
+                20.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                }
+            )
+            .with_step_size(0.1)
+            .with_unit(" ct"),
+            unison_detune_curve: FloatParam::new(
+                "Unison Detune Curve",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.25,
+                    max: 4.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                }
+            ),
+            unison_random_phase: FloatParam::new(
+                "Unison Random Phase",
+                1.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            unison_spread: FloatParam::new(
+                "Unison Stereo Spread",
+                0.5,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            delay: FloatParam::new(
+                "Delay",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 5000.0
+                })
+                .with_unit(" ms"),
             attack: FloatParam::new(
                 "Attack",
                 10.0,
@@ -130,6 +1082,14 @@ impl Default for SimpleSynthParams {
                     max: 5000.0
                 })
                 .with_unit(" ms"),
+            hold: FloatParam::new(
+                "Hold",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 5000.0
+                })
+                .with_unit(" ms"),
             decay: FloatParam::new(
                 "Decay",
                 100.0,
@@ -153,6 +1113,37 @@ impl Default for SimpleSynthParams {
                     max: 10000.0
                 })
                 .with_unit(" ms"),
+            attack_curve: FloatParam::new(
+                "Attack Curve",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            decay_curve: FloatParam::new(
+                "Decay Curve",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            release_curve: FloatParam::new(
+                "Release Curve",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            env_sync: BoolParam::new("Env Sync", false),
+            delay_division: EnumParam::new("Delay Division", NoteDivisionParam::Eighth),
+            attack_division: EnumParam::new("Attack Division", NoteDivisionParam::Sixteenth),
+            hold_division: EnumParam::new("Hold Division", NoteDivisionParam::Eighth),
+            decay_division: EnumParam::new("Decay Division", NoteDivisionParam::Quarter),
+            release_division: EnumParam::new("Release Division", NoteDivisionParam::Quarter),
+            retrigger_mode: EnumParam::new("Retrigger Mode", RetriggerModeParam::Reset),
             filter_mode: EnumParam::new("Filter mode", FilterModeParam::Lowpass),
             cutoff: FloatParam::new(
                 "Cutoff",
@@ -170,6 +1161,15 @@ impl Default for SimpleSynthParams {
                     max: 1.0,
                 }
             ),
+            filter_gain: FloatParam::new(
+                "Filter Gain",
+                0.0,
+                FloatRange::Linear {
+                    min: -24.0,
+                    max: 24.0,
+                }
+            )
+            .with_unit(" dB"),
             filter_env_amount: FloatParam::new(
                 "Filter Env Amount",
                 0.0,
@@ -178,6 +1178,102 @@ impl Default for SimpleSynthParams {
                     max: 1.0,
                 }
             ),
+            filter_env_type: EnumParam::new("Filter Env Type", FilterEnvTypeParam::Adsr),
+            // Slow swell cycling between two levels while the key is held.
+            breakpoints: [
+                BreakpointParams::new(500.0, 1.0, 0.5),
+                BreakpointParams::new(1000.0, 0.4, 0.5),
+                BreakpointParams::new(1500.0, 0.8, 0.0),
+                BreakpointParams::new(1500.0, 0.4, 0.0),
+                BreakpointParams::new(1000.0, 0.0, 0.5),
+                BreakpointParams::new(1000.0, 0.0, 0.0),
+                BreakpointParams::new(1000.0, 0.0, 0.0),
+                BreakpointParams::new(1000.0, 0.0, 0.0),
+            ],
+            breakpoint_count: IntParam::new(
+                "Breakpoint Count",
+                5,
+                IntRange::Linear {
+                    min: 1,
+                    max: BREAKPOINT_PARAMS as i32,
+                }
+            ),
+            breakpoint_sustain: IntParam::new(
+                "Breakpoint Sustain",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: BREAKPOINT_PARAMS as i32,
+                }
+            )
+            .with_value_to_string(Arc::new(|point| if point == 0 { String::from("Off") } else { point.to_string() })),
+            breakpoint_loop: BoolParam::new("Breakpoint Loop", true),
+            breakpoint_loop_start: IntParam::new(
+                "Breakpoint Loop Start",
+                2,
+                IntRange::Linear {
+                    min: 1,
+                    max: BREAKPOINT_PARAMS as i32,
+                }
+            ),
+            breakpoint_loop_end: IntParam::new(
+                "Breakpoint Loop End",
+                4,
+                IntRange::Linear {
+                    min: 1,
+                    max: BREAKPOINT_PARAMS as i32,
+                }
+            ),
+            breakpoint_pitch: FloatParam::new(
+                "Breakpoint Pitch",
+                0.0,
+                FloatRange::Linear {
+                    min: -48.0,
+                    max: 48.0,
+                }
+            )
+            .with_step_size(0.01)
+            .with_unit(" st"),
+            breakpoint_cutoff: FloatParam::new(
+                "Breakpoint Cutoff",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            breakpoint_mix: FloatParam::new(
+                "Breakpoint Osc Mix",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            breakpoint_pulse_width: FloatParam::new(
+                "Breakpoint Pulse Width",
+                0.0,
+                FloatRange::Linear {
+                    min: -0.49,
+                    max: 0.49,
+                }
+            ),
+            breakpoint_shape: FloatParam::new(
+                "Breakpoint Shape",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            filter_delay: FloatParam::new(
+                "Filter Delay",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 5000.0
+                })
+                .with_unit(" ms"),
             filter_attack: FloatParam::new(
                 "Attack",
                 10.0,
@@ -186,6 +1282,14 @@ impl Default for SimpleSynthParams {
                     max: 5000.0
                 })
                 .with_unit(" ms"),
+            filter_hold: FloatParam::new(
+                "Filter Hold",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 5000.0
+                })
+                .with_unit(" ms"),
             filter_decay: FloatParam::new(
                 "Decay",
                 100.0,
@@ -209,6 +1313,61 @@ impl Default for SimpleSynthParams {
                     max: 10000.0
                 })
                 .with_unit(" ms"),
+            filter_attack_curve: FloatParam::new(
+                "Filter Attack Curve",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            filter_decay_curve: FloatParam::new(
+                "Filter Decay Curve",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            filter_release_curve: FloatParam::new(
+                "Filter Release Curve",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                }
+            ),
+            filter_env_sync: BoolParam::new("Filter Env Sync", false),
+            filter_delay_division: EnumParam::new("Filter Delay Division", NoteDivisionParam::Eighth),
+            filter_attack_division: EnumParam::new("Filter Attack Division", NoteDivisionParam::Sixteenth),
+            filter_hold_division: EnumParam::new("Filter Hold Division", NoteDivisionParam::Eighth),
+            filter_decay_division: EnumParam::new("Filter Decay Division", NoteDivisionParam::Eighth),
+            filter_release_division: EnumParam::new("Filter Release Division", NoteDivisionParam::Quarter),
+            velocity_curve: EnumParam::new("Velocity Curve", VelocityCurveParam::Linear),
+            velocity_amp: FloatParam::new(
+                "Velocity Amp",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            velocity_filter_env: FloatParam::new(
+                "Velocity Filter Env",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
+            velocity_attack: FloatParam::new(
+                "Velocity Attack",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                }
+            ),
         }
     }
 }
@@ -233,7 +1392,7 @@ impl Plugin for SimpleSynth {
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
     type SysExMessage = ();
-    type BackgroundTask = ();
+    type BackgroundTask = PluginTask;
 
     fn initialize(
         &mut self,
@@ -242,17 +1401,50 @@ impl Plugin for SimpleSynth {
         _context: &mut impl InitContext<Self>,
     ) -> bool {
         self.voice_manager.apply_param(SynthParam::SampleRate(buffer_config.sample_rate));
+
+        // Reloads the sample saved with the state, the audio thread is not running yet.
+        let path = self.params.sample_path.read().map(|path| path.clone()).unwrap_or_default();
+        if !path.is_empty() {
+            match Sample::load(&path) {
+                Ok(sample) => {
+                    let sample = Arc::new(sample);
+                    self.voice_manager.set_sample(sample.clone());
+                    self.sample = Some(sample);
+                },
+                Err(error) => nih_log!("Could not load sample {path}: {error}"),
+            }
+        }
         true
     }
 
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        let sample_path = self.params.sample_path.clone();
+        let sample_slot = self.sample_slot.clone();
+        Box::new(move |task| match task {
+            PluginTask::LoadSample(path) => match Sample::load(&path) {
+                Ok(sample) => {
+                    if let Ok(mut slot) = sample_slot.lock() {
+                        slot.retired = None;
+                        slot.pending = Some(Arc::new(sample));
+                    }
+                    if let Ok(mut sample_path) = sample_path.write() {
+                        *sample_path = path;
+                    }
+                },
+                Err(error) => nih_log!("Could not load sample {path}: {error}"),
+            },
+        })
+    }
+
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
     }
 
-    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+    fn editor(&mut self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         editor::create(
             self.params.clone(),
             self.params.editor_state.clone(),
+            async_executor,
         )
     }
 
@@ -264,8 +1456,8 @@ impl Plugin for SimpleSynth {
     ) -> ProcessStatus {
          while let Some(event) = context.next_event() {
             match event {
-                NoteEvent::NoteOn { note, .. } => {
-                    self.voice_manager.note_on(note);
+                NoteEvent::NoteOn { note, velocity, .. } => {
+                    self.voice_manager.note_on(note, velocity);
                 }
                 NoteEvent::NoteOff { note, .. } => {
                     self.voice_manager.note_off(note);
@@ -274,26 +1466,140 @@ impl Plugin for SimpleSynth {
             }
         }
 
-        self.voice_manager.apply_param(SynthParam::Waveform(self.params.waveform.value().into()));
-        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Attack, self.params.attack.value() / 1000.0));
-        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Decay, self.params.decay.value() / 1000.0));
+        // Synced times follow the host tempo, or 120 BPM when the host does not report a usable one.
+        let tempo = context.transport().tempo.filter(|tempo| *tempo > 0.0).map_or(120.0, |tempo| tempo as f32);
+        let env_time = |milliseconds: &FloatParam, division: &EnumParam<NoteDivisionParam>, sync: bool| {
+            if sync { division.value().seconds(tempo) } else { milliseconds.value() / 1000.0 }
+        };
+        let env_sync = self.params.env_sync.value();
+        let filter_env_sync = self.params.filter_env_sync.value();
+
+        let waveform = self.params.waveform.value();
+        self.voice_manager.apply_param(SynthParam::OscillatorType(waveform.into()));
+        if let Some(waveform) = waveform.waveform() {
+            self.voice_manager.apply_param(SynthParam::Waveform(waveform));
+        }
+        self.voice_manager.apply_param(SynthParam::WavetablePosition(self.params.wavetable_position.value()));
+        self.voice_manager.apply_param(SynthParam::PluckExcitation(self.params.pluck_excitation.value().into()));
+        self.voice_manager.apply_param(SynthParam::PluckDamping(self.params.pluck_damping.value()));
+        self.voice_manager.apply_param(SynthParam::PluckBrightness(self.params.pluck_brightness.value()));
+        self.voice_manager.apply_param(SynthParam::PluckDecay(self.params.pluck_decay.value()));
+        self.voice_manager.apply_param(SynthParam::AdditivePartials(self.params.additive_partials.value() as usize));
+        self.voice_manager.apply_param(SynthParam::AdditiveBrightness(self.params.additive_brightness.value()));
+        self.voice_manager.apply_param(SynthParam::AdditiveOddEven(self.params.additive_odd_even.value()));
+        self.voice_manager.apply_param(SynthParam::AdditiveStretch(self.params.additive_stretch.value()));
+        // Skips the hand-over while the loader holds the slot, the sample is picked up next block.
+        if let Ok(mut slot) = self.sample_slot.try_lock()
+            && let Some(sample) = slot.pending.take()
+        {
+            self.voice_manager.set_sample(sample.clone());
+            slot.retired = self.sample.replace(sample);
+        }
+        self.voice_manager.apply_param(SynthParam::SampleInterpolation(self.params.sample_interpolation.value().into()));
+        self.voice_manager.apply_param(SynthParam::SampleLoopMode(self.params.sample_loop_mode.value().into()));
+        self.voice_manager.apply_param(SynthParam::SampleLoopStart(self.params.sample_loop_start.value()));
+        self.voice_manager.apply_param(SynthParam::SampleLoopEnd(self.params.sample_loop_end.value()));
+        self.voice_manager.apply_param(SynthParam::SampleCrossfade(self.params.sample_crossfade.value()));
+        self.voice_manager.apply_param(SynthParam::SampleRootKey(self.params.sample_root_key.value() as u8));
+        self.voice_manager.apply_param(SynthParam::SampleFineTune(self.params.sample_fine_tune.value()));
+        let osc2_waveform = self.params.osc2_waveform.value();
+        self.voice_manager.apply_param(SynthParam::Oscillator2Type(osc2_waveform.into()));
+        if let Some(waveform) = osc2_waveform.waveform() {
+            self.voice_manager.apply_param(SynthParam::Oscillator2Waveform(waveform));
+        }
+        self.voice_manager.apply_param(SynthParam::Oscillator2Octave(self.params.osc2_octave.value()));
+        self.voice_manager.apply_param(SynthParam::Oscillator2Semitone(self.params.osc2_semitone.value()));
+        self.voice_manager.apply_param(SynthParam::Oscillator2Fine(self.params.osc2_fine.value()));
+        self.voice_manager.apply_param(SynthParam::OscillatorMix(self.params.osc_mix.value()));
+        self.voice_manager.apply_param(SynthParam::SubWaveform(self.params.sub_waveform.value().into()));
+        self.voice_manager.apply_param(SynthParam::SubOctave(self.params.sub_octave.value()));
+        self.voice_manager.apply_param(SynthParam::SubLevel(self.params.sub_level.value()));
+        self.voice_manager.apply_param(SynthParam::OscillatorSync(self.params.osc_sync.value()));
+        self.voice_manager.apply_param(SynthParam::SyncPitch(self.params.sync_pitch.value()));
+        self.voice_manager.apply_param(SynthParam::SyncEnvAmount(self.params.sync_env_amount.value()));
+        self.voice_manager.apply_param(SynthParam::FmMode(self.params.fm_mode.value().into()));
+        self.voice_manager.apply_param(SynthParam::FmIndex(self.params.fm_index.value()));
+        self.voice_manager.apply_param(SynthParam::RingMod(self.params.ring_mod.value()));
+        self.voice_manager.apply_param(SynthParam::PulseWidth(self.params.pulse_width.value()));
+        self.voice_manager.apply_param(SynthParam::PwmDepth(self.params.pwm_depth.value()));
+        let pwm_rate = if self.params.pwm_sync.value() {
+            1.0 / self.params.pwm_division.value().seconds(tempo)
+        } else {
+            self.params.pwm_rate.value()
+        };
+        self.voice_manager.apply_param(SynthParam::PwmRate(pwm_rate));
+        self.voice_manager.apply_param(SynthParam::ShapeMode(self.params.shape_mode.value().into()));
+        self.voice_manager.apply_param(SynthParam::ShapeAmount(self.params.shape_amount.value()));
+        self.voice_manager.apply_param(SynthParam::ShapeEnvAmount(self.params.shape_env_amount.value()));
+        self.voice_manager.apply_param(SynthParam::DriftAmount(self.params.drift_amount.value()));
+        self.voice_manager.apply_param(SynthParam::DriftRate(self.params.drift_rate.value()));
+        self.voice_manager.apply_param(SynthParam::RandomDetune(self.params.random_detune.value()));
+        self.voice_manager.apply_param(SynthParam::PhaseMode(self.params.osc1_phase_mode.value().into()));
+        self.voice_manager.apply_param(SynthParam::Oscillator2PhaseMode(self.params.osc2_phase_mode.value().into()));
+        self.voice_manager.apply_param(SynthParam::UnisonVoices(self.params.unison_voices.value() as usize));
+        self.voice_manager.apply_param(SynthParam::UnisonDetune(self.params.unison_detune.value()));
+        self.voice_manager.apply_param(SynthParam::UnisonDetuneCurve(self.params.unison_detune_curve.value()));
+        self.voice_manager.apply_param(SynthParam::UnisonRandomPhase(self.params.unison_random_phase.value()));
+        self.voice_manager.apply_param(SynthParam::UnisonStereoSpread(self.params.unison_spread.value()));
+        self.voice_manager.apply_param(SynthParam::OscillatorQuality(self.params.quality.value().into()));
+        self.voice_manager.apply_param(SynthParam::NoiseColor(self.params.noise_color.value().into()));
+        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Delay, env_time(&self.params.delay, &self.params.delay_division, env_sync)));
+        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Attack, env_time(&self.params.attack, &self.params.attack_division, env_sync)));
+        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Hold, env_time(&self.params.hold, &self.params.hold_division, env_sync)));
+        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Decay, env_time(&self.params.decay, &self.params.decay_division, env_sync)));
         self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Sustain, self.params.sustain.value()));
-        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Release, self.params.release.value() / 1000.0));
+        self.voice_manager.apply_param(SynthParam::EnvelopeStage(EnvelopeStage::Release, env_time(&self.params.release, &self.params.release_division, env_sync)));
+        self.voice_manager.apply_param(SynthParam::EnvelopeCurve(EnvelopeStage::Attack, self.params.attack_curve.value()));
+        self.voice_manager.apply_param(SynthParam::EnvelopeCurve(EnvelopeStage::Decay, self.params.decay_curve.value()));
+        self.voice_manager.apply_param(SynthParam::EnvelopeCurve(EnvelopeStage::Release, self.params.release_curve.value()));
+        self.voice_manager.apply_param(SynthParam::RetriggerMode(self.params.retrigger_mode.value().into()));
         self.voice_manager.apply_param(SynthParam::FilterMode(self.params.filter_mode.value().into()));
         self.voice_manager.apply_param(SynthParam::Cutoff(self.params.cutoff.value()));
-        self.voice_manager.apply_param(SynthParam::Resonance(self.params.release.value()));
+        self.voice_manager.apply_param(SynthParam::Resonance(self.params.resonance.value()));
+        self.voice_manager.apply_param(SynthParam::FilterGain(self.params.filter_gain.value()));
         self.voice_manager.apply_param(SynthParam::FilterEnvAmount(self.params.filter_env_amount.value()));
-        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Attack, self.params.filter_attack.value() / 1000.0));
-        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Decay, self.params.filter_decay.value() / 1000.0));
+        self.voice_manager.apply_param(SynthParam::FilterEnvType(self.params.filter_env_type.value().into()));
+        let mut breakpoints = [Breakpoint::default(); BREAKPOINT_PARAMS];
+        for (breakpoint, params) in breakpoints.iter_mut().zip(&self.params.breakpoints) {
+            *breakpoint = Breakpoint::new(params.time.value() / 1000.0, params.level.value(), params.curve.value());
+        }
+        let count = self.params.breakpoint_count.value() as usize;
+        // The params count breakpoints from 1, a sustain point of 0 being off.
+        let sustain = (self.params.breakpoint_sustain.value() as usize).checked_sub(1);
+        let loop_points = self.params.breakpoint_loop.value().then(|| {
+            (self.params.breakpoint_loop_start.value() as usize - 1, self.params.breakpoint_loop_end.value() as usize - 1)
+        });
+        self.voice_manager.set_breakpoints(&breakpoints[..count], sustain, loop_points);
+        self.voice_manager.apply_param(SynthParam::BreakpointPitch(self.params.breakpoint_pitch.value()));
+        self.voice_manager.apply_param(SynthParam::BreakpointCutoff(self.params.breakpoint_cutoff.value()));
+        self.voice_manager.apply_param(SynthParam::BreakpointMix(self.params.breakpoint_mix.value()));
+        self.voice_manager.apply_param(SynthParam::BreakpointPulseWidth(self.params.breakpoint_pulse_width.value()));
+        self.voice_manager.apply_param(SynthParam::BreakpointShape(self.params.breakpoint_shape.value()));
+        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Delay, env_time(&self.params.filter_delay, &self.params.filter_delay_division, filter_env_sync)));
+        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Attack, env_time(&self.params.filter_attack, &self.params.filter_attack_division, filter_env_sync)));
+        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Hold, env_time(&self.params.filter_hold, &self.params.filter_hold_division, filter_env_sync)));
+        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Decay, env_time(&self.params.filter_decay, &self.params.filter_decay_division, filter_env_sync)));
         self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Sustain, self.params.filter_sustain.value()));
-        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Release, self.params.filter_release.value() / 1000.0));
+        self.voice_manager.apply_param(SynthParam::FilterEnvStage(EnvelopeStage::Release, env_time(&self.params.filter_release, &self.params.filter_release_division, filter_env_sync)));
+        self.voice_manager.apply_param(SynthParam::FilterEnvCurve(EnvelopeStage::Attack, self.params.filter_attack_curve.value()));
+        self.voice_manager.apply_param(SynthParam::FilterEnvCurve(EnvelopeStage::Decay, self.params.filter_decay_curve.value()));
+        self.voice_manager.apply_param(SynthParam::FilterEnvCurve(EnvelopeStage::Release, self.params.filter_release_curve.value()));
+        self.voice_manager.apply_param(SynthParam::VelocityCurve(self.params.velocity_curve.value().into()));
+        self.voice_manager.apply_param(SynthParam::VelocityAmplitude(self.params.velocity_amp.value()));
+        self.voice_manager.apply_param(SynthParam::VelocityFilterEnv(self.params.velocity_filter_env.value()));
+        self.voice_manager.apply_param(SynthParam::VelocityAttack(self.params.velocity_attack.value()));
+
+        // The bank only speeds up plain patches (basic oscillators, linear ADSR envelopes and
+        // one-pole filters), anything else renders voice by voice at a higher CPU cost.
+        if let [left, right, ..] = buffer.as_slice() {
+            self.voice_manager.process_bank(left, right);
+        }
 
         for channel_samples in buffer.iter_samples() {
             let gain = self.params.gain.smoothed.next();
             let gain = db_to_gain(gain);
             for sample in channel_samples {
-                let output = self.voice_manager.process_voices();
-                *sample = output * util::db_to_gain_fast(gain);
+                *sample *= util::db_to_gain_fast(gain);
             }
         }
 